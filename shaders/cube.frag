@@ -2,9 +2,15 @@
 
 out vec4 color;
 in vec2 vertex_tex_coord;
+flat in float vertex_texture_layer;
 in vec3 vertex_normal;
+in vec2 vertex_light;
+in float vertex_ambient_occlusion;
 
-uniform sampler2D tex_sampler;
+uniform sampler2DArray tex_sampler;
+uniform float opacity = 1.0;
+// Set to 0.0 to turn off ambient occlusion.
+uniform float ambient_occlusion_strength = 1.0;
 
 const float PI = 3.1415926535897932384626433832795;
 
@@ -14,12 +20,27 @@ const float sun_pitch = PI / 3;
 const float diffuse_factor = 0.5;
 const float ambient_factor = 0.5;
 
+// Each level of light is this much dimmer than the level above it, down to the brightness of a
+// block with no light at all.
+const float light_falloff = 0.8;
+const float min_brightness = 0.04;
+
+// The brightness of a fully occluded corner.
+const float min_ambient_occlusion = 0.4;
+
 void main() {
   vec3 sun_direction = vec3(sin(sun_pitch) * cos(sun_heading), sin(sun_pitch) * sin(sun_heading), cos(sun_pitch));
 
-  vec4 frag_color = texture(tex_sampler, vertex_tex_coord);
+  vec4 frag_color = texture(tex_sampler, vec3(vertex_tex_coord, vertex_texture_layer));
   vec4 diffuse_color = frag_color * max(dot(vertex_normal, sun_direction), 0.0) * diffuse_factor;
   vec4 ambient_color = frag_color * ambient_factor;
 
-  color = diffuse_color + ambient_color;
+  // Light levels are stored as fractions of the brightest level.
+  float light_level = max(vertex_light.x, vertex_light.y) * 15.0;
+  float brightness = max(pow(light_falloff, 15.0 - light_level), min_brightness);
+
+  float occlusion = mix(min_ambient_occlusion, 1.0, vertex_ambient_occlusion);
+  brightness *= mix(1.0, occlusion, ambient_occlusion_strength);
+
+  color = vec4((diffuse_color + ambient_color).rgb * brightness, opacity);
 }
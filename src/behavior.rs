@@ -0,0 +1,94 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::{
+    block::BlockId,
+    math::RandomNumberGenerator,
+    world::{Coordinates, World},
+};
+
+/// How far leaves can be from a block of wood, counting steps through other leaves, before they
+/// start to decay.
+const LEAF_SUPPORT_DISTANCE: u32 = 4;
+
+/// The range of the number of ticks before the leaves next to a decayed block of leaves check
+/// whether they should decay too.
+const MIN_LEAF_DECAY_DELAY: u32 = 10;
+const MAX_LEAF_DECAY_DELAY: u32 = 40;
+
+/// Grass that's been covered up dies back to dirt. Otherwise it spreads to a nearby block of
+/// dirt, as long as that dirt isn't covered up either.
+pub(crate) fn spread_grass(
+    world: &mut World,
+    coordinates: &Coordinates,
+    rng: &mut RandomNumberGenerator,
+) {
+    if !is_uncovered(world, coordinates) {
+        world.set_block(coordinates, BlockId::DIRT);
+        return;
+    }
+
+    let target = coordinates.offset([
+        rng.gen_range(0, 3) as i32 - 1,
+        rng.gen_range(0, 5) as i32 - 3,
+        rng.gen_range(0, 3) as i32 - 1,
+    ]);
+
+    if world.get_block(&target) == BlockId::DIRT && is_uncovered(world, &target) {
+        world.set_block(&target, BlockId::GRASS);
+    }
+}
+
+/// Whether light and air can reach the top of the block.
+fn is_uncovered(world: &World, coordinates: &Coordinates) -> bool {
+    let above = world.get_block(&coordinates.offset([0, 1, 0]));
+    above.block_type().transparent && above.fluid_level().is_none()
+}
+
+/// Leaves that have been cut off from the wood of their tree decay. Once they do, the leaves
+/// around them are checked soon after, so that a whole canopy falls apart in quick succession.
+pub(crate) fn decay_leaves(
+    world: &mut World,
+    coordinates: &Coordinates,
+    rng: &mut RandomNumberGenerator,
+) {
+    if is_leaf_supported(world, coordinates) {
+        return;
+    }
+
+    world.set_block(coordinates, BlockId::AIR);
+
+    for neighbor in coordinates.neighbors() {
+        if world.get_block(&neighbor) == BlockId::LEAVES {
+            let delay = rng.gen_range(MIN_LEAF_DECAY_DELAY, MAX_LEAF_DECAY_DELAY + 1);
+            world.schedule_tick(&neighbor, delay as u64);
+        }
+    }
+}
+
+/// Searches through the leaves around the given leaves for wood within `LEAF_SUPPORT_DISTANCE`.
+/// Leaves that reach into chunks that aren't loaded are treated as supported, since the wood may
+/// be in those chunks.
+fn is_leaf_supported(world: &World, coordinates: &Coordinates) -> bool {
+    let mut visited = HashSet::from([*coordinates]);
+    let mut queue = VecDeque::from([(*coordinates, 0)]);
+
+    while let Some((leaves, distance)) = queue.pop_front() {
+        for neighbor in leaves.neighbors() {
+            if !world.is_loaded(&neighbor) {
+                return true;
+            }
+
+            match world.get_block(&neighbor) {
+                BlockId::WOOD => return true,
+                BlockId::LEAVES
+                    if distance + 1 < LEAF_SUPPORT_DISTANCE && visited.insert(neighbor) =>
+                {
+                    queue.push_back((neighbor, distance + 1));
+                }
+                _ => (),
+            }
+        }
+    }
+
+    false
+}
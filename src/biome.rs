@@ -0,0 +1,227 @@
+use crate::{
+    block::BlockId,
+    structure::{self, Structure},
+};
+
+/// The climate of a position in the world. Both values are within [-1.0, 1.0], and most of the
+/// world is within [-0.5, 0.5].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct Climate {
+    pub(crate) temperature: f32,
+    pub(crate) humidity: f32,
+}
+
+impl Climate {
+    fn distance(&self, other: &Climate) -> f32 {
+        let temperature = self.temperature - other.temperature;
+        let humidity = self.humidity - other.humidity;
+        (temperature * temperature + humidity * humidity).sqrt()
+    }
+}
+
+/// Biomes whose climates are within this distance of the closest biome's climate have a say in
+/// the height of the terrain, so that the height changes gradually across the border between two
+/// biomes instead of jumping from one to the other.
+const BLEND_DISTANCE: f32 = 0.08;
+
+/// The kind of landscape found in a part of the world.
+#[derive(Debug)]
+pub(crate) struct Biome {
+    pub(crate) name: &'static str,
+    /// The climate that the biome is found in. Each position belongs to the biome with the
+    /// closest climate.
+    climate: Climate,
+    /// The average height of the terrain in the biome, as a fraction of the world's height.
+    pub(crate) height: f32,
+    /// How much the heightmap raises and lowers the terrain around its average height. Flat
+    /// biomes have a small scale, and hilly biomes have a large one.
+    pub(crate) height_scale: f32,
+    /// The block on top of the ground.
+    pub(crate) surface_block: BlockId,
+    /// The blocks between the surface and the stone below it.
+    pub(crate) filler_block: BlockId,
+    pub(crate) decorations: &'static [Decoration],
+}
+
+/// A structure placed on top of the ground here and there across a biome.
+#[derive(Debug)]
+pub(crate) struct Decoration {
+    /// The chance of the structure being placed on each surface block, from 0.0 to 1.0.
+    pub(crate) chance: f32,
+    pub(crate) structure: &'static Structure,
+}
+
+/// Every biome, checked in order when choosing the biome for a climate.
+static BIOMES: [Biome; 4] = [
+    Biome {
+        name: "plains",
+        climate: Climate {
+            temperature: 0.0,
+            humidity: 0.0,
+        },
+        height: 0.35,
+        height_scale: 0.4,
+        surface_block: BlockId::GRASS,
+        filler_block: BlockId::DIRT,
+        decorations: &[
+            Decoration {
+                chance: 0.002,
+                structure: &structure::TREE,
+            },
+            Decoration {
+                chance: 0.01,
+                structure: &structure::BUSH,
+            },
+            Decoration {
+                chance: 0.001,
+                structure: &structure::BOULDER,
+            },
+        ],
+    },
+    Biome {
+        name: "forest",
+        climate: Climate {
+            temperature: 0.05,
+            humidity: 0.25,
+        },
+        height: 0.4,
+        height_scale: 0.6,
+        surface_block: BlockId::GRASS,
+        filler_block: BlockId::DIRT,
+        decorations: &[
+            Decoration {
+                chance: 0.03,
+                structure: &structure::TREE,
+            },
+            Decoration {
+                chance: 0.02,
+                structure: &structure::BUSH,
+            },
+        ],
+    },
+    Biome {
+        name: "desert",
+        climate: Climate {
+            temperature: 0.25,
+            humidity: -0.2,
+        },
+        height: 0.3,
+        height_scale: 0.3,
+        surface_block: BlockId::SAND,
+        filler_block: BlockId::SAND,
+        decorations: &[Decoration {
+            chance: 0.002,
+            structure: &structure::BOULDER,
+        }],
+    },
+    Biome {
+        name: "mountains",
+        climate: Climate {
+            temperature: -0.25,
+            humidity: 0.0,
+        },
+        height: 0.55,
+        height_scale: 1.4,
+        surface_block: BlockId::STONE,
+        filler_block: BlockId::STONE,
+        decorations: &[Decoration {
+            chance: 0.01,
+            structure: &structure::BOULDER,
+        }],
+    },
+];
+
+/// Returns the biome whose climate is closest to the given climate.
+pub(crate) fn biome_for(climate: &Climate) -> &'static Biome {
+    BIOMES
+        .iter()
+        .min_by(|a, b| {
+            let a_distance = a.climate.distance(climate);
+            let b_distance = b.climate.distance(climate);
+            a_distance.total_cmp(&b_distance)
+        })
+        .unwrap()
+}
+
+/// Returns how much each biome should influence the terrain in the given climate. The closest
+/// biome always has a weight of 1.0, and other biomes have less weight the further away they are.
+/// Biomes that have no influence are left out.
+pub(crate) fn biome_weights(climate: &Climate) -> Vec<(&'static Biome, f32)> {
+    let closest_distance = biome_for(climate).climate.distance(climate);
+
+    BIOMES
+        .iter()
+        .filter_map(|biome| {
+            let distance = biome.climate.distance(climate) - closest_distance;
+            let weight = 1.0 - distance / BLEND_DISTANCE;
+
+            if weight > 0.0 {
+                Some((biome, weight))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn biomes_are_chosen_by_the_closest_climate() {
+        let examples = [
+            (0.0, 0.0, "plains"),
+            (0.05, 0.4, "forest"),
+            (0.6, -0.6, "desert"),
+            (-0.5, 0.3, "mountains"),
+        ];
+
+        for (temperature, humidity, expected_name) in examples.into_iter() {
+            let climate = Climate {
+                temperature,
+                humidity,
+            };
+
+            assert_eq!(biome_for(&climate).name, expected_name);
+        }
+    }
+
+    #[test]
+    fn biomes_blend_near_borders() {
+        // Far from any border, only the closest biome has a say.
+        let weights = biome_weights(&BIOMES[3].climate);
+        assert_eq!(weights.len(), 1);
+        assert_eq!(weights[0].0.name, "mountains");
+
+        // Exactly halfway between two biomes, both have an equal say.
+        let plains = &BIOMES[0].climate;
+        let mountains = &BIOMES[3].climate;
+        let border = Climate {
+            temperature: (plains.temperature + mountains.temperature) / 2.0,
+            humidity: (plains.humidity + mountains.humidity) / 2.0,
+        };
+
+        let weights = biome_weights(&border);
+        let names: Vec<&str> = weights.iter().map(|(biome, _)| biome.name).collect();
+        assert_eq!(names, ["plains", "mountains"]);
+        assert!((weights[0].1 - weights[1].1).abs() < 0.0001);
+    }
+
+    #[test]
+    fn decorations_are_not_certain() {
+        for biome in BIOMES.iter() {
+            for decoration in biome.decorations.iter() {
+                assert!(
+                    decoration.chance > 0.0 && decoration.chance < 1.0,
+                    "{} has a decoration with an invalid chance",
+                    biome.name
+                );
+            }
+
+            // Each surface block gets at most one decoration, so the chances share a single roll.
+            let total_chance: f32 = biome.decorations.iter().map(|d| d.chance).sum();
+            assert!(total_chance < 1.0, "{} is always decorated", biome.name);
+        }
+    }
+}
@@ -0,0 +1,393 @@
+use crate::{behavior, tick::TickCallback};
+
+/// Identifies the type of block stored at a single position in the world. The ID is an index into
+/// the registry of block types.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct BlockId(u8);
+
+impl BlockId {
+    pub(crate) const AIR: BlockId = BlockId(0);
+    pub(crate) const STONE: BlockId = BlockId(1);
+    pub(crate) const DIRT: BlockId = BlockId(2);
+    pub(crate) const GRASS: BlockId = BlockId(3);
+    pub(crate) const SAND: BlockId = BlockId(4);
+    pub(crate) const WATER: BlockId = BlockId(5);
+    pub(crate) const WOOD: BlockId = BlockId(6);
+    pub(crate) const LEAVES: BlockId = BlockId(7);
+    pub(crate) const LAMP: BlockId = BlockId(8);
+    pub(crate) const COAL_ORE: BlockId = BlockId(9);
+    pub(crate) const IRON_ORE: BlockId = BlockId(10);
+    pub(crate) const GOLD_ORE: BlockId = BlockId(11);
+    pub(crate) const DIAMOND_ORE: BlockId = BlockId(12);
+    pub(crate) const GRAVEL: BlockId = BlockId(20);
+
+    /// Returns the water block with the given fluid level, where level 0 is a source.
+    pub(crate) fn water(level: u8) -> Self {
+        assert!(
+            level <= MAX_FLUID_LEVEL,
+            "water level {} is too high",
+            level
+        );
+
+        if level == 0 {
+            Self::WATER
+        } else {
+            Self(FLOWING_WATER_START + level - 1)
+        }
+    }
+
+    /// Returns the block with the given registry index, if there is one.
+    pub(crate) fn from_index(index: u8) -> Option<Self> {
+        if (index as usize) < BLOCK_TYPES.len() {
+            Some(Self(index))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the first block in the registry with the given name, if there is one.
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        BLOCK_TYPES
+            .iter()
+            .position(|block_type| block_type.name == name)
+            .map(|index| Self(index as u8))
+    }
+
+    pub(crate) fn index(&self) -> u8 {
+        self.0
+    }
+
+    pub(crate) fn block_type(&self) -> &'static BlockType {
+        &BLOCK_TYPES[self.0 as usize]
+    }
+
+    pub(crate) fn fluid_level(&self) -> Option<u8> {
+        self.block_type().fluid_level
+    }
+
+    /// Whether a falling block can fall into this block, replacing it.
+    pub(crate) fn can_fall_into(&self) -> bool {
+        *self == Self::AIR || self.fluid_level().is_some()
+    }
+}
+
+/// The highest level that a fluid can reach. Fluids stop spreading sideways once they're this
+/// far from a source.
+pub(crate) const MAX_FLUID_LEVEL: u8 = 7;
+
+/// The properties shared by every block of the same type.
+#[derive(Debug)]
+pub(crate) struct BlockType {
+    pub(crate) name: &'static str,
+    /// Whether entities collide with the block.
+    pub(crate) solid: bool,
+    /// Whether neighboring blocks can be seen through the block.
+    pub(crate) transparent: bool,
+    /// Whether the block is drawn partly see-through, blended over the blocks behind it.
+    pub(crate) translucent: bool,
+    /// For fluids, how far the block is from a source block of the fluid. Sources have a level of
+    /// 0, and the level goes up by one for each block that the fluid spreads sideways.
+    pub(crate) fluid_level: Option<u8>,
+    /// Whether the block falls when there's nothing beneath it to hold it up.
+    pub(crate) falls: bool,
+    /// The amount of light the block gives off, from 0 (none) up to 15.
+    pub(crate) light_emission: u8,
+    /// The layers of the block texture array drawn on the faces of the block.
+    pub(crate) textures: BlockTextures,
+    /// Called every so often on blocks picked at random, for behavior that happens gradually.
+    pub(crate) random_tick: Option<TickCallback>,
+    /// Called when a tick that was scheduled for the block comes due.
+    pub(crate) scheduled_tick: Option<TickCallback>,
+}
+
+/// The layers of the block texture array drawn on each side of a block.
+#[derive(Debug)]
+pub(crate) struct BlockTextures {
+    pub(crate) top: u32,
+    /// Drawn on all four of the faces around the sides of the block.
+    pub(crate) side: u32,
+    pub(crate) bottom: u32,
+}
+
+impl BlockTextures {
+    /// The same texture on every face of the block.
+    const fn all(layer: u32) -> Self {
+        Self {
+            top: layer,
+            side: layer,
+            bottom: layer,
+        }
+    }
+}
+
+/// The index in the registry of flowing water with a level of 1. The other levels follow it in
+/// order, up to `MAX_FLUID_LEVEL`.
+const FLOWING_WATER_START: u8 = 13;
+
+/// Flowing water at the given level, which looks the same as the water source it flows from.
+const fn flowing_water(level: u8) -> BlockType {
+    BlockType {
+        name: "flowing water",
+        solid: false,
+        transparent: true,
+        translucent: true,
+        fluid_level: Some(level),
+        falls: false,
+        light_emission: 0,
+        textures: BlockTextures::all(4),
+        random_tick: None,
+        scheduled_tick: None,
+    }
+}
+
+/// Every known block type, indexed by `BlockId`.
+static BLOCK_TYPES: [BlockType; 21] = [
+    BlockType {
+        name: "air",
+        solid: false,
+        transparent: true,
+        translucent: false,
+        fluid_level: None,
+        falls: false,
+        light_emission: 0,
+        textures: BlockTextures::all(0),
+        random_tick: None,
+        scheduled_tick: None,
+    },
+    BlockType {
+        name: "stone",
+        solid: true,
+        transparent: false,
+        translucent: false,
+        fluid_level: None,
+        falls: false,
+        light_emission: 0,
+        textures: BlockTextures::all(0),
+        random_tick: None,
+        scheduled_tick: None,
+    },
+    BlockType {
+        name: "dirt",
+        solid: true,
+        transparent: false,
+        translucent: false,
+        fluid_level: None,
+        falls: false,
+        light_emission: 0,
+        textures: BlockTextures::all(1),
+        random_tick: None,
+        scheduled_tick: None,
+    },
+    BlockType {
+        name: "grass",
+        solid: true,
+        transparent: false,
+        translucent: false,
+        fluid_level: None,
+        falls: false,
+        light_emission: 0,
+        textures: BlockTextures {
+            top: 2,
+            side: 13,
+            bottom: 1,
+        },
+        random_tick: Some(behavior::spread_grass),
+        scheduled_tick: None,
+    },
+    BlockType {
+        name: "sand",
+        solid: true,
+        transparent: false,
+        translucent: false,
+        fluid_level: None,
+        falls: true,
+        light_emission: 0,
+        textures: BlockTextures::all(3),
+        random_tick: None,
+        scheduled_tick: None,
+    },
+    BlockType {
+        name: "water",
+        solid: false,
+        transparent: true,
+        translucent: true,
+        fluid_level: Some(0),
+        falls: false,
+        light_emission: 0,
+        textures: BlockTextures::all(4),
+        random_tick: None,
+        scheduled_tick: None,
+    },
+    BlockType {
+        name: "wood",
+        solid: true,
+        transparent: false,
+        translucent: false,
+        fluid_level: None,
+        falls: false,
+        light_emission: 0,
+        textures: BlockTextures {
+            top: 14,
+            side: 5,
+            bottom: 14,
+        },
+        random_tick: None,
+        scheduled_tick: None,
+    },
+    BlockType {
+        name: "leaves",
+        solid: true,
+        transparent: true,
+        translucent: false,
+        fluid_level: None,
+        falls: false,
+        light_emission: 0,
+        textures: BlockTextures::all(6),
+        random_tick: Some(behavior::decay_leaves),
+        scheduled_tick: Some(behavior::decay_leaves),
+    },
+    BlockType {
+        name: "lamp",
+        solid: true,
+        transparent: false,
+        translucent: false,
+        fluid_level: None,
+        falls: false,
+        light_emission: 15,
+        textures: BlockTextures::all(7),
+        random_tick: None,
+        scheduled_tick: None,
+    },
+    BlockType {
+        name: "coal ore",
+        solid: true,
+        transparent: false,
+        translucent: false,
+        fluid_level: None,
+        falls: false,
+        light_emission: 0,
+        textures: BlockTextures::all(8),
+        random_tick: None,
+        scheduled_tick: None,
+    },
+    BlockType {
+        name: "iron ore",
+        solid: true,
+        transparent: false,
+        translucent: false,
+        fluid_level: None,
+        falls: false,
+        light_emission: 0,
+        textures: BlockTextures::all(9),
+        random_tick: None,
+        scheduled_tick: None,
+    },
+    BlockType {
+        name: "gold ore",
+        solid: true,
+        transparent: false,
+        translucent: false,
+        fluid_level: None,
+        falls: false,
+        light_emission: 0,
+        textures: BlockTextures::all(10),
+        random_tick: None,
+        scheduled_tick: None,
+    },
+    BlockType {
+        name: "diamond ore",
+        solid: true,
+        transparent: false,
+        translucent: false,
+        fluid_level: None,
+        falls: false,
+        light_emission: 0,
+        textures: BlockTextures::all(11),
+        random_tick: None,
+        scheduled_tick: None,
+    },
+    flowing_water(1),
+    flowing_water(2),
+    flowing_water(3),
+    flowing_water(4),
+    flowing_water(5),
+    flowing_water(6),
+    flowing_water(7),
+    BlockType {
+        name: "gravel",
+        solid: true,
+        transparent: false,
+        translucent: false,
+        fluid_level: None,
+        falls: true,
+        light_emission: 0,
+        textures: BlockTextures::all(12),
+        random_tick: None,
+        scheduled_tick: None,
+    },
+];
+
+/// The number of distinct layers referenced by `BlockType::textures`.
+pub(crate) const NUM_BLOCK_TEXTURES: usize = 15;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn block_types_refer_to_existing_textures() {
+        for block_type in BLOCK_TYPES.iter() {
+            let BlockTextures { top, side, bottom } = block_type.textures;
+
+            for layer in [top, side, bottom] {
+                assert!(
+                    (layer as usize) < NUM_BLOCK_TEXTURES,
+                    "{} has an invalid texture index",
+                    block_type.name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn water_blocks_have_matching_levels() {
+        for level in 0..=MAX_FLUID_LEVEL {
+            let block = BlockId::water(level);
+            assert_eq!(block.fluid_level(), Some(level));
+            assert!(block.block_type().translucent);
+        }
+
+        assert_eq!(BlockId::water(0), BlockId::WATER);
+        assert_eq!(BlockId::water(1).index(), FLOWING_WATER_START);
+        assert_eq!(BlockId::STONE.fluid_level(), None);
+    }
+
+    #[test]
+    fn flowing_water_starts_where_the_registry_says() {
+        for level in 1..=MAX_FLUID_LEVEL {
+            let block_type = &BLOCK_TYPES[(FLOWING_WATER_START + level - 1) as usize];
+            assert_eq!(block_type.name, "flowing water");
+            assert_eq!(block_type.fluid_level, Some(level));
+        }
+    }
+
+    #[test]
+    fn block_ids_match_registry_entries() {
+        let examples = [
+            (BlockId::AIR, "air"),
+            (BlockId::STONE, "stone"),
+            (BlockId::GRASS, "grass"),
+            (BlockId::LAMP, "lamp"),
+            (BlockId::DIAMOND_ORE, "diamond ore"),
+            (BlockId::GRAVEL, "gravel"),
+        ];
+
+        for (block, expected_name) in examples.into_iter() {
+            assert_eq!(block.block_type().name, expected_name);
+            assert_eq!(BlockId::from_name(expected_name), Some(block));
+        }
+
+        assert_eq!(BlockId::from_name("flowing water"), Some(BlockId::water(1)));
+        assert_eq!(BlockId::from_name("marble"), None);
+    }
+}
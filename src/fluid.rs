@@ -0,0 +1,182 @@
+use crate::{
+    block::{BlockId, MAX_FLUID_LEVEL},
+    world::Coordinates,
+};
+
+/// The number of world updates between each step of the fluid simulation, which sets how fast
+/// fluids flow.
+pub(crate) const FLUID_TICK_INTERVAL: u64 = 5;
+
+const HORIZONTAL_OFFSETS: [[i32; 3]; 4] = [[1, 0, 0], [-1, 0, 0], [0, 0, 1], [0, 0, -1]];
+
+/// Returns the block that should be at the given coordinates after the next step of the fluid
+/// simulation, given a way to look up the blocks around it.
+///
+/// Fluid falls straight down through air, and only spreads sideways once it lands on something,
+/// going up a level with each block until it reaches `MAX_FLUID_LEVEL`. Flowing fluid that's cut
+/// off from its source keeps rising in level until it dries up.
+pub(crate) fn next_block(
+    coordinates: &Coordinates,
+    get_block: impl Fn(&Coordinates) -> BlockId,
+) -> BlockId {
+    let block = get_block(coordinates);
+
+    // Sources never dry up, and fluids only flow into air or other flowing fluid.
+    if block == BlockId::WATER || (block != BlockId::AIR && block.fluid_level().is_none()) {
+        return block;
+    }
+
+    let below = get_block(&coordinates.offset([0, -1, 0]));
+    let mut level = None;
+    let mut num_sources = 0;
+
+    if get_block(&coordinates.offset([0, 1, 0]))
+        .fluid_level()
+        .is_some()
+    {
+        level = Some(1);
+    }
+
+    for offset in HORIZONTAL_OFFSETS {
+        let neighbor = coordinates.offset(offset);
+        let Some(neighbor_level) = get_block(&neighbor).fluid_level() else {
+            continue;
+        };
+
+        if neighbor_level == 0 {
+            num_sources += 1;
+        }
+
+        if neighbor_level < MAX_FLUID_LEVEL
+            && holds_up_fluid(get_block(&neighbor.offset([0, -1, 0])))
+        {
+            let spread_level = neighbor_level + 1;
+            level = Some(level.map_or(spread_level, |level: u8| level.min(spread_level)));
+        }
+    }
+
+    // Fluid between two sources that can't drain away below becomes a source itself, so that
+    // digging a channel out of a lake fills it up for good.
+    if num_sources >= 2 && holds_up_fluid(below) {
+        return BlockId::WATER;
+    }
+
+    match level {
+        Some(level) => BlockId::water(level),
+        None => BlockId::AIR,
+    }
+}
+
+/// Whether fluid resting on top of the block spreads sideways, rather than falling through it.
+fn holds_up_fluid(block: BlockId) -> bool {
+    block == BlockId::WATER || (block != BlockId::AIR && block.fluid_level().is_none())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    /// A floor of stone along y = 0 with air everywhere above it, plus the given blocks.
+    struct Pool(HashMap<Coordinates, BlockId>);
+
+    impl Pool {
+        fn new(blocks: &[(Coordinates, BlockId)]) -> Self {
+            Self(blocks.iter().copied().collect())
+        }
+
+        fn get_block(&self, coordinates: &Coordinates) -> BlockId {
+            match self.0.get(coordinates) {
+                Some(block) => *block,
+                None if coordinates.1 <= 0 => BlockId::STONE,
+                None => BlockId::AIR,
+            }
+        }
+
+        /// Steps every block in a box around the origin at the same time.
+        fn step(&mut self, steps: u32) {
+            for _ in 0..steps {
+                let mut changes = Vec::new();
+
+                for x in -10..=10 {
+                    for y in 1..=5 {
+                        for z in -10..=10 {
+                            let coordinates = Coordinates(x, y, z);
+                            let next = next_block(&coordinates, |c| self.get_block(c));
+
+                            if next != self.get_block(&coordinates) {
+                                changes.push((coordinates, next));
+                            }
+                        }
+                    }
+                }
+
+                self.0.extend(changes);
+            }
+        }
+    }
+
+    #[test]
+    fn water_spreads_across_the_ground_until_it_runs_out() {
+        let mut pool = Pool::new(&[(Coordinates(0, 1, 0), BlockId::WATER)]);
+        pool.step(10);
+
+        for distance in 1..=MAX_FLUID_LEVEL as i32 {
+            let level = pool.get_block(&Coordinates(distance, 1, 0)).fluid_level();
+            assert_eq!(level, Some(distance as u8));
+
+            let level = pool.get_block(&Coordinates(0, 1, -distance)).fluid_level();
+            assert_eq!(level, Some(distance as u8));
+        }
+
+        assert_eq!(
+            pool.get_block(&Coordinates(MAX_FLUID_LEVEL as i32 + 1, 1, 0)),
+            BlockId::AIR
+        );
+        assert_eq!(pool.get_block(&Coordinates(0, 2, 0)), BlockId::AIR);
+    }
+
+    #[test]
+    fn water_falls_before_it_spreads() {
+        let mut pool = Pool::new(&[
+            (Coordinates(0, 4, 0), BlockId::WATER),
+            (Coordinates(0, 3, 0), BlockId::STONE),
+            (Coordinates(1, 3, 0), BlockId::STONE),
+        ]);
+        pool.step(12);
+
+        // The water runs off the edge of the ledge and falls to the ground, without spreading
+        // while it's in the air.
+        assert_eq!(pool.get_block(&Coordinates(1, 4, 0)), BlockId::water(1));
+        assert_eq!(pool.get_block(&Coordinates(2, 4, 0)), BlockId::water(2));
+        assert_eq!(pool.get_block(&Coordinates(3, 4, 0)), BlockId::AIR);
+        assert_eq!(pool.get_block(&Coordinates(2, 3, 0)), BlockId::water(1));
+        assert_eq!(pool.get_block(&Coordinates(2, 2, 1)), BlockId::AIR);
+        assert_eq!(pool.get_block(&Coordinates(2, 1, 0)), BlockId::water(1));
+        assert_eq!(pool.get_block(&Coordinates(2, 1, 1)), BlockId::water(2));
+    }
+
+    #[test]
+    fn water_between_two_sources_becomes_a_source() {
+        let mut pool = Pool::new(&[
+            (Coordinates(-1, 1, 0), BlockId::WATER),
+            (Coordinates(1, 1, 0), BlockId::WATER),
+        ]);
+        pool.step(1);
+
+        assert_eq!(pool.get_block(&Coordinates(0, 1, 0)), BlockId::WATER);
+        assert_eq!(pool.get_block(&Coordinates(1, 1, 1)), BlockId::water(1));
+    }
+
+    #[test]
+    fn flowing_water_dries_up_without_a_source() {
+        let mut pool = Pool::new(&[(Coordinates(0, 1, 0), BlockId::WATER)]);
+        pool.step(10);
+
+        pool.0.insert(Coordinates(0, 1, 0), BlockId::AIR);
+        pool.step(20);
+
+        assert!(pool.0.values().all(|block| *block == BlockId::AIR));
+    }
+}
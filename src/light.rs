@@ -0,0 +1,277 @@
+use std::collections::VecDeque;
+
+use crate::{block::BlockId, world::Coordinates};
+
+/// The brightest that light can be. Sunlight has this level wherever the sky can be seen, and
+/// light drops by one level for each block that it spreads through.
+pub(crate) const MAX_LIGHT: u8 = 15;
+
+const NEIGHBOR_OFFSETS: [[i32; 3]; 6] = [
+    [1, 0, 0],
+    [-1, 0, 0],
+    [0, 1, 0],
+    [0, -1, 0],
+    [0, 0, 1],
+    [0, 0, -1],
+];
+
+/// The two kinds of light that each block keeps track of. They spread separately, and whichever
+/// is brighter is the one that's seen.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum LightChannel {
+    /// Light from the sky, which shines straight down without fading until it hits something.
+    Sky,
+    /// Light given off by blocks such as lamps.
+    Block,
+}
+
+impl LightChannel {
+    pub(crate) const ALL: [LightChannel; 2] = [LightChannel::Sky, LightChannel::Block];
+}
+
+/// Somewhere that light can spread through, such as the world.
+pub(crate) trait LightVolume {
+    /// Returns the block at the given coordinates, or `None` if it isn't loaded. Light never
+    /// spreads into blocks that aren't loaded.
+    fn block(&self, coordinates: &Coordinates) -> Option<BlockId>;
+
+    fn light(&self, coordinates: &Coordinates, channel: LightChannel) -> u8;
+
+    fn set_light(&mut self, coordinates: &Coordinates, channel: LightChannel, level: u8);
+}
+
+/// Spreads the light of every block in the queue into the transparent blocks around them, and
+/// on from there until it fades out or meets light that's at least as bright.
+pub(crate) fn spread_light(
+    volume: &mut impl LightVolume,
+    channel: LightChannel,
+    mut queue: VecDeque<Coordinates>,
+) {
+    while let Some(coordinates) = queue.pop_front() {
+        let level = volume.light(&coordinates, channel);
+
+        for offset in NEIGHBOR_OFFSETS {
+            let neighbor = coordinates.offset(offset);
+            let spread_level = spread_level(channel, level, offset);
+
+            let Some(block) = volume.block(&neighbor) else {
+                continue;
+            };
+
+            if block.block_type().transparent && spread_level > volume.light(&neighbor, channel) {
+                volume.set_light(&neighbor, channel, spread_level);
+                queue.push_back(neighbor);
+            }
+        }
+    }
+}
+
+/// Darkens the block at the given coordinates, along with every block around it that got its
+/// light from it. Returns the lit blocks around the edges of the darkened area, which need to
+/// spread their light back into it with `spread_light`.
+pub(crate) fn remove_light(
+    volume: &mut impl LightVolume,
+    channel: LightChannel,
+    coordinates: &Coordinates,
+) -> VecDeque<Coordinates> {
+    let mut relight = VecDeque::new();
+    let mut queue = VecDeque::from([(*coordinates, volume.light(coordinates, channel))]);
+    volume.set_light(coordinates, channel, 0);
+
+    while let Some((coordinates, level)) = queue.pop_front() {
+        for offset in NEIGHBOR_OFFSETS {
+            let neighbor = coordinates.offset(offset);
+            let neighbor_level = volume.light(&neighbor, channel);
+
+            if neighbor_level == 0 {
+                continue;
+            }
+
+            // Light that's dimmer than the removed light could have come from it, as could
+            // sunlight that shone straight down from it. Anything else has its own source.
+            if neighbor_level < level
+                || (neighbor_level == level && spread_level(channel, level, offset) == level)
+            {
+                volume.set_light(&neighbor, channel, 0);
+                queue.push_back((neighbor, neighbor_level));
+
+                // Blocks that give off light keep it, and spread it back afterwards.
+                let emitted = volume
+                    .block(&neighbor)
+                    .map_or(0, |block| emitted_light(block, channel));
+                if emitted > 0 {
+                    volume.set_light(&neighbor, channel, emitted);
+                    relight.push_back(neighbor);
+                }
+            } else {
+                relight.push_back(neighbor);
+            }
+        }
+    }
+
+    relight
+}
+
+/// Updates the light around a block that has just been replaced by another block. This only
+/// needs to be called when the new block lets through or gives off a different amount of light.
+pub(crate) fn update_light(volume: &mut impl LightVolume, coordinates: &Coordinates) {
+    let Some(block) = volume.block(coordinates) else {
+        return;
+    };
+
+    for channel in LightChannel::ALL {
+        let mut queue = remove_light(volume, channel, coordinates);
+
+        let emitted = emitted_light(block, channel);
+        if emitted > 0 {
+            volume.set_light(coordinates, channel, emitted);
+            queue.push_back(*coordinates);
+        }
+
+        // The blocks around a transparent block spread their light back into it.
+        if block.block_type().transparent {
+            queue.extend(
+                NEIGHBOR_OFFSETS
+                    .iter()
+                    .map(|offset| coordinates.offset(*offset)),
+            );
+        }
+
+        spread_light(volume, channel, queue);
+    }
+}
+
+/// Returns the amount of light that the block gives off in the given channel.
+pub(crate) fn emitted_light(block: BlockId, channel: LightChannel) -> u8 {
+    match channel {
+        LightChannel::Sky => 0,
+        LightChannel::Block => block.block_type().light_emission,
+    }
+}
+
+/// Returns how bright light becomes after spreading one block along the offset.
+fn spread_level(channel: LightChannel, level: u8, offset: [i32; 3]) -> u8 {
+    if channel == LightChannel::Sky && level == MAX_LIGHT && offset == [0, -1, 0] {
+        MAX_LIGHT
+    } else {
+        level.saturating_sub(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    /// A box of blocks from (0, 0, 0) to (15, 15, 15), which starts out empty and completely
+    /// dark.
+    struct Room {
+        blocks: HashMap<Coordinates, BlockId>,
+        light: HashMap<(Coordinates, LightChannel), u8>,
+    }
+
+    impl Room {
+        fn new() -> Self {
+            Self {
+                blocks: HashMap::new(),
+                light: HashMap::new(),
+            }
+        }
+
+        fn place(&mut self, coordinates: &Coordinates, block: BlockId) {
+            self.blocks.insert(*coordinates, block);
+            update_light(self, coordinates);
+        }
+
+        /// Lets sunlight in through the top of the room.
+        fn open_to_the_sky(&mut self) {
+            let mut queue = VecDeque::new();
+
+            for x in 0..16 {
+                for z in 0..16 {
+                    let coordinates = Coordinates(x, 15, z);
+
+                    if self.block(&coordinates) == Some(BlockId::AIR) {
+                        self.set_light(&coordinates, LightChannel::Sky, MAX_LIGHT);
+                        queue.push_back(coordinates);
+                    }
+                }
+            }
+
+            spread_light(self, LightChannel::Sky, queue);
+        }
+    }
+
+    impl LightVolume for Room {
+        fn block(&self, coordinates: &Coordinates) -> Option<BlockId> {
+            let Coordinates(x, y, z) = *coordinates;
+
+            if [x, y, z].iter().all(|c| (0..16).contains(c)) {
+                Some(*self.blocks.get(coordinates).unwrap_or(&BlockId::AIR))
+            } else {
+                None
+            }
+        }
+
+        fn light(&self, coordinates: &Coordinates, channel: LightChannel) -> u8 {
+            *self.light.get(&(*coordinates, channel)).unwrap_or(&0)
+        }
+
+        fn set_light(&mut self, coordinates: &Coordinates, channel: LightChannel, level: u8) {
+            self.light.insert((*coordinates, channel), level);
+        }
+    }
+
+    #[test]
+    fn block_light_fades_with_distance_and_goes_out_when_removed() {
+        let mut room = Room::new();
+        room.place(&Coordinates(8, 8, 8), BlockId::LAMP);
+
+        assert_eq!(room.light(&Coordinates(8, 8, 8), LightChannel::Block), 15);
+        assert_eq!(room.light(&Coordinates(9, 8, 8), LightChannel::Block), 14);
+        assert_eq!(room.light(&Coordinates(10, 6, 8), LightChannel::Block), 11);
+        assert_eq!(room.light(&Coordinates(0, 8, 8), LightChannel::Block), 7);
+        assert_eq!(room.light(&Coordinates(8, 8, 8), LightChannel::Sky), 0);
+
+        // Light goes around walls rather than through them.
+        room.place(&Coordinates(9, 8, 8), BlockId::STONE);
+        assert_eq!(room.light(&Coordinates(9, 8, 8), LightChannel::Block), 0);
+        assert_eq!(room.light(&Coordinates(10, 8, 8), LightChannel::Block), 11);
+
+        // A second lamp keeps its own light when the first one is removed.
+        room.place(&Coordinates(2, 8, 8), BlockId::LAMP);
+        room.place(&Coordinates(8, 8, 8), BlockId::AIR);
+        assert_eq!(room.light(&Coordinates(8, 8, 8), LightChannel::Block), 9);
+        assert_eq!(room.light(&Coordinates(2, 8, 8), LightChannel::Block), 15);
+
+        room.place(&Coordinates(2, 8, 8), BlockId::AIR);
+        assert!(room.light.values().all(|level| *level == 0));
+    }
+
+    #[test]
+    fn sunlight_shines_down_until_it_is_blocked() {
+        let mut room = Room::new();
+        for x in 0..16 {
+            for z in 0..16 {
+                room.blocks.insert(Coordinates(x, 10, z), BlockId::STONE);
+            }
+        }
+        room.open_to_the_sky();
+
+        assert_eq!(room.light(&Coordinates(3, 11, 3), LightChannel::Sky), 15);
+        assert_eq!(room.light(&Coordinates(3, 9, 3), LightChannel::Sky), 0);
+
+        // Opening a hole in the roof lets the sunlight all the way down to the floor, and it
+        // spreads out sideways from there.
+        room.place(&Coordinates(3, 10, 3), BlockId::AIR);
+        assert_eq!(room.light(&Coordinates(3, 0, 3), LightChannel::Sky), 15);
+        assert_eq!(room.light(&Coordinates(5, 0, 3), LightChannel::Sky), 13);
+
+        // Covering the hole back up shuts it out again.
+        room.place(&Coordinates(3, 12, 3), BlockId::STONE);
+        assert_eq!(room.light(&Coordinates(3, 12, 3), LightChannel::Sky), 0);
+        assert_eq!(room.light(&Coordinates(3, 11, 3), LightChannel::Sky), 14);
+        assert_eq!(room.light(&Coordinates(3, 0, 3), LightChannel::Sky), 3);
+    }
+}
@@ -1,31 +1,163 @@
+mod behavior;
+mod biome;
+mod block;
+mod fluid;
+mod light;
 mod math;
+mod mesh;
+mod ore;
+mod physics;
 mod render;
+mod resource;
+mod storage;
+mod structure;
+mod terrain;
+mod tick;
 mod time;
+mod worker;
 mod world;
 
-use std::time::{Duration, Instant};
+use std::{
+    path::PathBuf,
+    str::FromStr,
+    time::{Duration, Instant},
+};
 
+use block::BlockId;
+use mesh::MeshingMode;
+use ore::OreStatistics;
 use render::Renderer;
+use storage::WorldStorage;
+use terrain::TerrainGenerator;
 use time::FrameCounter;
 use winit::{
-    event::{DeviceEvent, ElementState, Event, KeyEvent, WindowEvent},
+    event::{DeviceEvent, ElementState, Event, KeyEvent, MouseButton, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
     keyboard::{KeyCode, PhysicalKey},
 };
-use world::World;
+use world::{World, WorldOptions, CHUNK_SIZE};
 
 const FRAMES_PER_SECOND: u64 = 60;
 const NANOSECONDS_PER_SECOND: u64 = 1_000_000_000;
 const FRAME_DURATION: Duration = Duration::from_nanos(NANOSECONDS_PER_SECOND / FRAMES_PER_SECOND);
 
+const USAGE: &str = "\
+Usage: iridium [OPTIONS]
+
+Display options:
+  -w, --windowed            Run in a window instead of fullscreen
+  -v, --no-vsync            Don't wait for vertical sync
+  -d, --view-distance <N>   Load chunks within N chunks of the camera (0-32, default 8)
+  -n, --naive-meshing       Start with one quad per block face instead of greedy meshing
+      --fov <N>             Angle between the left and right edges of the screen, in degrees
+                            (10-170, default 90)
+      --assets <DIR>        Load block textures from the resource pack in DIR, generating any
+                            that it doesn't have (default assets)
+
+World options:
+      --world <DIR>         Save the world in DIR, and load it from there if it exists. A saved
+                            world keeps the seed, size, height and noise options it was made with
+      --seed <N>            Seed for the terrain generator (default 32131)
+      --size <N>            Limit the world to N by N blocks, where N is a multiple of 16
+                            (default: unlimited)
+      --height <N>          Height of the tallest terrain, in blocks (1-255, default 32)
+      --noise <NAME>        Noise algorithm for the heightmap, either perlin or simplex (default
+                            perlin)
+      --cell-size <N>       Width of each cell of the first octave of heightmap noise, in blocks
+                            (default 32)
+      --octaves <N>         Number of octaves of heightmap noise (1-16, default 4)
+      --lacunarity <N>      How much smaller each octave's cells are than the last (at least 1,
+                            default 2)
+      --persistence <N>     How much each octave counts compared to the last (above 0 and at most
+                            1, default 0.5)
+      --cave-cell-size <N>  Width of each cell of the cave noise, in blocks (default 16)
+      --cave-threshold <N>  Carve caves where the cave noise is below N, from 0 (no caves) to 1
+                            (default 0.37)
+      --cave-bottom <N>     Lowest y coordinate that caves can reach (default 1)
+      --cave-top <N>        Highest y coordinate that caves can reach (default 24)
+      --biome-cell-size <N> Width of each cell of the climate noise that decides the biomes, in
+                            blocks (default 256)
+      --sea-level <N>       Fill the terrain with water up to this y coordinate (default 11)
+
+      --ore-stats <N>       Generate N by N columns with the world options, print the number of
+                            each ore found at each depth, and exit
+
+  -h, --help                Print this message
+";
+
+/// The most chunks uploaded to the GPU in a single frame, so that streaming in new chunks doesn't
+/// cause the frame rate to hitch.
+const MAX_CHUNK_UPLOADS_PER_FRAME: usize = 4;
+
+/// The blocks that can be placed, selected with the number keys in this order.
+const PLACEABLE_BLOCKS: [(KeyCode, BlockId); 9] = [
+    (KeyCode::Digit1, BlockId::STONE),
+    (KeyCode::Digit2, BlockId::DIRT),
+    (KeyCode::Digit3, BlockId::GRASS),
+    (KeyCode::Digit4, BlockId::SAND),
+    (KeyCode::Digit5, BlockId::WATER),
+    (KeyCode::Digit6, BlockId::WOOD),
+    (KeyCode::Digit7, BlockId::LEAVES),
+    (KeyCode::Digit8, BlockId::LAMP),
+    (KeyCode::Digit9, BlockId::GRAVEL),
+];
+
 fn main() {
-    let options = get_options();
+    let options = match get_options() {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("error: {}\n\n{}", error, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if let Some(size) = options.ore_statistics_size {
+        let generator = TerrainGenerator::new(options.world.terrain);
+        print!("{}", OreStatistics::collect(&generator, size));
+        return;
+    }
+
+    let block_textures = match resource::load_block_textures(&options.assets_directory) {
+        Ok(loaded) => {
+            for path in loaded.missing_files {
+                eprintln!("missing texture {}, using a generated one", path.display());
+            }
+            loaded.textures
+        }
+        Err(error) => {
+            eprintln!(
+                "failed to load textures from {}: {}",
+                options.assets_directory.display(),
+                error
+            );
+            std::process::exit(1);
+        }
+    };
 
     let event_loop = EventLoop::new().unwrap();
-    let mut renderer = Renderer::new(&event_loop, options.windowed, options.disable_vsync);
+    let mut renderer = Renderer::new(
+        &event_loop,
+        options.windowed,
+        options.disable_vsync,
+        options.field_of_view.to_radians(),
+        &block_textures,
+    );
 
-    let mut world = World::new(256, 32, 256);
-    renderer.update_block_cache(world.visible_block_positions());
+    let mut world = match &options.world_directory {
+        Some(world_directory) => {
+            let world = WorldStorage::open(world_directory)
+                .and_then(|storage| World::load(storage, options.world.clone()));
+
+            match world {
+                Ok(world) => world,
+                Err(error) => {
+                    eprintln!("failed to open {}: {}", world_directory.display(), error);
+                    std::process::exit(1);
+                }
+            }
+        }
+        None => World::new(options.world.clone()),
+    };
 
     let mut last_instant = Instant::now();
     let mut fps_counter = FrameCounter::new(last_instant);
@@ -89,12 +221,52 @@ fn main() {
                     (ElementState::Released, PhysicalKey::Code(KeyCode::ControlLeft)) => {
                         world.stop_moving_down()
                     }
+                    (ElementState::Pressed, PhysicalKey::Code(KeyCode::KeyM)) => {
+                        let meshing_mode = world.toggle_meshing_mode();
+                        println!("Meshing mode: {:?}", meshing_mode);
+                    }
+                    (ElementState::Pressed, PhysicalKey::Code(KeyCode::KeyO)) => {
+                        let ambient_occlusion = renderer.toggle_ambient_occlusion();
+                        println!("Ambient occlusion: {}", ambient_occlusion);
+                    }
+                    (ElementState::Pressed, PhysicalKey::Code(KeyCode::KeyF)) => {
+                        let movement_mode = world.toggle_movement_mode();
+                        println!("Movement mode: {:?}", movement_mode);
+                    }
+                    (ElementState::Pressed, PhysicalKey::Code(KeyCode::KeyB)) => {
+                        let (biome, climate) = world.biome_at_camera();
+                        println!(
+                            "Biome: {} (temperature {:.2}, humidity {:.2})",
+                            biome.name, climate.temperature, climate.humidity
+                        );
+                    }
                     (ElementState::Pressed, PhysicalKey::Code(KeyCode::Escape)) => {
                         window_target.exit();
                     }
+                    (ElementState::Pressed, PhysicalKey::Code(key_code)) => {
+                        if let Some((_, block)) =
+                            PLACEABLE_BLOCKS.iter().find(|(key, _)| *key == key_code)
+                        {
+                            world.select_block(*block);
+                            println!("Selected block: {}", block.block_type().name);
+                        }
+                    }
                     _ => {}
                 };
             }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::MouseInput {
+                        state: ElementState::Pressed,
+                        button,
+                        ..
+                    },
+                window_id,
+            } if window_id == renderer.window_id() => match button {
+                MouseButton::Left => world.remove_block_at_target(),
+                MouseButton::Right => world.place_block_at_target(),
+                _ => {}
+            },
             Event::DeviceEvent {
                 event: DeviceEvent::MouseMotion { delta: (dx, dy) },
                 ..
@@ -115,14 +287,24 @@ fn main() {
                     current_instant = Instant::now();
                 }
 
+                for update in world.take_chunk_updates(MAX_CHUNK_UPLOADS_PER_FRAME) {
+                    renderer.apply_chunk_update(update);
+                }
+
                 renderer.set_camera(world.camera());
                 renderer.clear();
 
                 renderer.draw_cubes();
                 renderer.draw_skybox();
+                renderer.draw_translucent_cubes();
 
                 renderer.present();
-                fps_counter.finish_frame(current_instant);
+                fps_counter.finish_frame(current_instant, renderer.statistics());
+            }
+            Event::LoopExiting => {
+                if let Err(error) = world.save() {
+                    eprintln!("failed to save the world: {}", error);
+                }
             }
             _ => (),
         })
@@ -132,14 +314,153 @@ fn main() {
 struct GameOptions {
     windowed: bool,
     disable_vsync: bool,
+    /// The horizontal field of view, in degrees.
+    field_of_view: f32,
+    world: WorldOptions,
+    /// Where the world is saved, if it is saved at all.
+    world_directory: Option<PathBuf>,
+    /// The resource pack that block textures are loaded from.
+    assets_directory: PathBuf,
+    /// The number of columns along each side of the square to collect ore statistics from, if
+    /// only the statistics were asked for.
+    ore_statistics_size: Option<u32>,
 }
 
-fn get_options() -> GameOptions {
-    let args: Vec<String> = std::env::args().collect();
-    let windowed = args.iter().any(|arg| arg == "-w" || arg == "--windowed");
-    let disable_vsync = args.iter().any(|arg| arg == "-v" || arg == "--no-vsync");
-    GameOptions {
-        windowed,
-        disable_vsync,
+/// Reads the options from the command line, checking that they are valid. Prints the usage and
+/// exits if help was requested.
+fn get_options() -> Result<GameOptions, String> {
+    let mut options = GameOptions {
+        windowed: false,
+        disable_vsync: false,
+        field_of_view: 90.0,
+        world: WorldOptions::default(),
+        world_directory: None,
+        assets_directory: PathBuf::from("assets"),
+        ore_statistics_size: None,
+    };
+
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                print!("{}", USAGE);
+                std::process::exit(0);
+            }
+            "-w" | "--windowed" => options.windowed = true,
+            "-v" | "--no-vsync" => options.disable_vsync = true,
+            "-n" | "--naive-meshing" => options.world.meshing_mode = MeshingMode::Naive,
+            "-d" | "--view-distance" => {
+                let view_distance = parse_value(&arg, args.next())?;
+                if view_distance > 32 {
+                    return Err(format!("{} must be at most 32", arg));
+                }
+                options.world.view_distance = view_distance;
+            }
+            "--assets" => options.assets_directory = parse_value(&arg, args.next())?,
+            "--fov" => {
+                let field_of_view = parse_value(&arg, args.next())?;
+                if !(10.0..=170.0).contains(&field_of_view) {
+                    return Err(format!("{} must be between 10 and 170", arg));
+                }
+                options.field_of_view = field_of_view;
+            }
+            "--world" => options.world_directory = Some(parse_value(&arg, args.next())?),
+            "--seed" => options.world.terrain.seed = parse_value(&arg, args.next())?,
+            "--size" => {
+                let size: u32 = parse_value(&arg, args.next())?;
+                if size == 0 || !size.is_multiple_of(CHUNK_SIZE as u32) {
+                    return Err(format!(
+                        "{} must be a positive multiple of {}",
+                        arg, CHUNK_SIZE
+                    ));
+                }
+                options.world.size = Some(size);
+            }
+            "--noise" => options.world.terrain.noise = parse_value(&arg, args.next())?,
+            "--height" => {
+                let height = parse_value(&arg, args.next())?;
+                if !(1..=255).contains(&height) {
+                    return Err(format!("{} must be between 1 and 255", arg));
+                }
+                options.world.terrain.max_height = height;
+            }
+            "--cell-size" => {
+                let cell_size: f32 = parse_value(&arg, args.next())?;
+                if !cell_size.is_finite() || cell_size <= 0.0 {
+                    return Err(format!("{} must be a positive number", arg));
+                }
+                options.world.terrain.cell_size = cell_size;
+            }
+            "--octaves" => {
+                let octaves = parse_value(&arg, args.next())?;
+                if !(1..=16).contains(&octaves) {
+                    return Err(format!("{} must be between 1 and 16", arg));
+                }
+                options.world.terrain.octaves = octaves;
+            }
+            "--lacunarity" => {
+                let lacunarity: f32 = parse_value(&arg, args.next())?;
+                if !lacunarity.is_finite() || lacunarity < 1.0 {
+                    return Err(format!("{} must be at least 1", arg));
+                }
+                options.world.terrain.lacunarity = lacunarity;
+            }
+            "--persistence" => {
+                let persistence: f32 = parse_value(&arg, args.next())?;
+                if !(persistence > 0.0 && persistence <= 1.0) {
+                    return Err(format!("{} must be above 0 and at most 1", arg));
+                }
+                options.world.terrain.persistence = persistence;
+            }
+            "--cave-cell-size" => {
+                let cave_cell_size: f32 = parse_value(&arg, args.next())?;
+                if !cave_cell_size.is_finite() || cave_cell_size <= 0.0 {
+                    return Err(format!("{} must be a positive number", arg));
+                }
+                options.world.terrain.cave_cell_size = cave_cell_size;
+            }
+            "--cave-threshold" => {
+                let cave_threshold: f32 = parse_value(&arg, args.next())?;
+                if !(0.0..=1.0).contains(&cave_threshold) {
+                    return Err(format!("{} must be between 0 and 1", arg));
+                }
+                options.world.terrain.cave_threshold = cave_threshold;
+            }
+            "--cave-bottom" => options.world.terrain.cave_bottom = parse_value(&arg, args.next())?,
+            "--cave-top" => options.world.terrain.cave_top = parse_value(&arg, args.next())?,
+            "--biome-cell-size" => {
+                let biome_cell_size: f32 = parse_value(&arg, args.next())?;
+                if !biome_cell_size.is_finite() || biome_cell_size <= 0.0 {
+                    return Err(format!("{} must be a positive number", arg));
+                }
+                options.world.terrain.biome_cell_size = biome_cell_size;
+            }
+            "--sea-level" => options.world.terrain.sea_level = parse_value(&arg, args.next())?,
+            "--ore-stats" => {
+                let size = parse_value(&arg, args.next())?;
+                if size == 0 {
+                    return Err(format!("{} must be at least 1", arg));
+                }
+                options.ore_statistics_size = Some(size);
+            }
+            _ => return Err(format!("unknown option {}", arg)),
+        }
+    }
+
+    let terrain = &options.world.terrain;
+    if terrain.cave_bottom > terrain.cave_top {
+        return Err("--cave-bottom must not be above --cave-top".to_string());
     }
+
+    Ok(options)
+}
+
+/// Parses the value given after an option.
+fn parse_value<T: FromStr>(option: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("{} needs a value", option))?;
+
+    value
+        .parse()
+        .map_err(|_| format!("invalid value for {}: {}", option, value))
 }
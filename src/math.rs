@@ -1,4 +1,4 @@
-use std::ops::{Add, Div, Sub};
+use std::ops::{Add, Div, Mul, Sub};
 
 #[derive(Debug, Copy, Clone)]
 pub(crate) struct Vec2(pub(crate) f32, pub(crate) f32);
@@ -29,7 +29,7 @@ impl Sub<Vec2> for Vec2 {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub(crate) struct Vec3(pub(crate) f32, pub(crate) f32, pub(crate) f32);
 
 impl Vec3 {
@@ -58,6 +58,27 @@ impl Vec3 {
         Self(new_x, new_y, new_z)
     }
 
+    pub(crate) fn dot(&self, rhs: &Vec3) -> f32 {
+        self.0 * rhs.0 + self.1 * rhs.1 + self.2 * rhs.2
+    }
+
+    pub(crate) fn cross(&self, rhs: &Vec3) -> Vec3 {
+        Vec3(
+            self.1 * rhs.2 - self.2 * rhs.1,
+            self.2 * rhs.0 - self.0 * rhs.2,
+            self.0 * rhs.1 - self.1 * rhs.0,
+        )
+    }
+
+    pub(crate) fn length(&self) -> f32 {
+        (self.0 * self.0 + self.1 * self.1 + self.2 * self.2).sqrt()
+    }
+
+    pub(crate) fn normalize(&self) -> Vec3 {
+        let length = self.length();
+        Self(self.0 / length, self.1 / length, self.2 / length)
+    }
+
     pub(crate) fn x(&self) -> f32 {
         self.0
     }
@@ -83,12 +104,220 @@ impl Add<Vec3> for Vec3 {
     }
 }
 
+impl Sub<Vec3> for Vec3 {
+    type Output = Vec3;
+
+    fn sub(self, rhs: Vec3) -> Self::Output {
+        Vec3(self.0 - rhs.0, self.1 - rhs.1, self.2 - rhs.2)
+    }
+}
+
+impl Mul<f32> for Vec3 {
+    type Output = Vec3;
+
+    fn mul(self, scale: f32) -> Self::Output {
+        Vec3(self.0 * scale, self.1 * scale, self.2 * scale)
+    }
+}
+
+/// A 4x4 matrix for transforming points in homogeneous coordinates, stored a row at a time.
+/// Points are treated as columns, so `a * b` transforms by `b` and then by `a`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct Mat4(pub(crate) [[f32; 4]; 4]);
+
+impl Mat4 {
+    /// Returns the projection from the space of a camera looking along the positive Z axis onto
+    /// the screen. The field of view is the angle between the left and right edges of the screen,
+    /// and the aspect ratio is the screen's width divided by its height. Points at the `near` and
+    /// `far` distances end up with depths of -1.0 and 1.0.
+    pub(crate) fn perspective(field_of_view: f32, aspect_ratio: f32, near: f32, far: f32) -> Self {
+        let scale = 1.0 / (field_of_view / 2.0).tan();
+
+        Self([
+            [scale, 0.0, 0.0, 0.0],
+            [0.0, scale * aspect_ratio, 0.0, 0.0],
+            [
+                0.0,
+                0.0,
+                (far + near) / (far - near),
+                -2.0 * far * near / (far - near),
+            ],
+            [0.0, 0.0, 1.0, 0.0],
+        ])
+    }
+
+    /// Returns the transform from the world into the space of a camera at `eye` looking towards
+    /// `target`, where the camera looks along the positive Z axis with the positive Y axis
+    /// pointing up. The `up` direction must not be parallel to the direction the camera looks.
+    pub(crate) fn look_at(eye: &Vec3, target: &Vec3, up: &Vec3) -> Self {
+        let forward = (*target - *eye).normalize();
+        let right = up.cross(&forward).normalize();
+        let up = forward.cross(&right);
+
+        Self([
+            [right.x(), right.y(), right.z(), -right.dot(eye)],
+            [up.x(), up.y(), up.z(), -up.dot(eye)],
+            [forward.x(), forward.y(), forward.z(), -forward.dot(eye)],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub(crate) fn transpose(&self) -> Self {
+        Self(std::array::from_fn(|row| {
+            std::array::from_fn(|column| self.0[column][row])
+        }))
+    }
+
+    /// Returns the matrix that undoes this one, or `None` if some points are transformed onto the
+    /// same point, so that it can't be undone.
+    pub(crate) fn inverse(&self) -> Option<Self> {
+        // Gauss-Jordan elimination, applying the same row operations to the identity matrix that
+        // turn this matrix into the identity.
+        let mut rows = self.0;
+        let mut inverse = [[0.0; 4]; 4];
+        for (i, row) in inverse.iter_mut().enumerate() {
+            row[i] = 1.0;
+        }
+
+        for column in 0..4 {
+            // Divide by the largest value available, to keep the rounding errors small.
+            let pivot = (column..4)
+                .max_by(|a, b| rows[*a][column].abs().total_cmp(&rows[*b][column].abs()))
+                .unwrap();
+            if rows[pivot][column].abs() < f32::EPSILON {
+                return None;
+            }
+
+            rows.swap(column, pivot);
+            inverse.swap(column, pivot);
+
+            let scale = 1.0 / rows[column][column];
+            for i in 0..4 {
+                rows[column][i] *= scale;
+                inverse[column][i] *= scale;
+            }
+
+            for row in 0..4 {
+                let factor = rows[row][column];
+                if row == column || factor == 0.0 {
+                    continue;
+                }
+
+                for i in 0..4 {
+                    rows[row][i] -= factor * rows[column][i];
+                    inverse[row][i] -= factor * inverse[column][i];
+                }
+            }
+        }
+
+        Some(Self(inverse))
+    }
+}
+
+impl Mul<Mat4> for Mat4 {
+    type Output = Mat4;
+
+    fn mul(self, rhs: Mat4) -> Self::Output {
+        Mat4(std::array::from_fn(|row| {
+            std::array::from_fn(|column| (0..4).map(|i| self.0[row][i] * rhs.0[i][column]).sum())
+        }))
+    }
+}
+
+/// A plane dividing space in two. Points on the side that the normal points towards are in
+/// front of the plane.
+#[derive(Debug, Copy, Clone)]
+struct Plane {
+    normal: Vec3,
+    distance: f32,
+}
+
+impl Plane {
+    /// Returns the plane of the points where `a * x + b * y + c * z + d` is zero, facing towards
+    /// the points where it's positive.
+    fn from_coefficients([a, b, c, d]: [f32; 4]) -> Self {
+        let length = Vec3(a, b, c).length();
+
+        Self {
+            normal: Vec3(a, b, c) * (1.0 / length),
+            distance: d / length,
+        }
+    }
+
+    /// Returns how far the point is in front of the plane. Points behind it give a negative
+    /// distance.
+    fn distance_to(&self, point: &Vec3) -> f32 {
+        self.normal.dot(point) + self.distance
+    }
+}
+
+/// The region of space that the camera can see, bounded by the near and far planes and the four
+/// edges of the screen.
+#[derive(Debug, Clone)]
+pub(crate) struct Frustum {
+    /// The planes around the edges of the frustum, facing inwards.
+    planes: [Plane; 6],
+}
+
+impl Frustum {
+    /// Returns the frustum seen through the given transform from the world onto the screen.
+    pub(crate) fn from_view_projection(view_projection: &Mat4) -> Self {
+        let [x, y, z, w] = view_projection.0;
+
+        // A point is on screen when each of its x, y and z coordinates is between -w and w.
+        let plane = |row: [f32; 4], sign: f32| {
+            Plane::from_coefficients(std::array::from_fn(|i| w[i] + sign * row[i]))
+        };
+
+        Self {
+            planes: [
+                plane(x, 1.0),
+                plane(x, -1.0),
+                plane(y, 1.0),
+                plane(y, -1.0),
+                plane(z, 1.0),
+                plane(z, -1.0),
+            ],
+        }
+    }
+
+    /// Whether any part of the axis-aligned box from `min` to `max` might be within the frustum.
+    /// Boxes near the corners of the frustum can be counted as inside when they aren't, but boxes
+    /// that are inside are never counted as outside.
+    pub(crate) fn intersects_box(&self, min: &Vec3, max: &Vec3) -> bool {
+        self.planes.iter().all(|plane| {
+            // The corner of the box furthest in front of the plane.
+            let corner = Vec3(
+                if plane.normal.x() >= 0.0 {
+                    max.x()
+                } else {
+                    min.x()
+                },
+                if plane.normal.y() >= 0.0 {
+                    max.y()
+                } else {
+                    min.y()
+                },
+                if plane.normal.z() >= 0.0 {
+                    max.z()
+                } else {
+                    min.z()
+                },
+            );
+
+            plane.distance_to(&corner) >= 0.0
+        })
+    }
+}
+
 pub(crate) struct RandomNumberGenerator {
     seed: u32,
 }
 
 impl RandomNumberGenerator {
     pub(crate) fn with_seed(seed: u32) -> Self {
+        // A xorshift generator seeded with zero only ever returns zero.
+        let seed = if seed == 0 { 0x9e37_79b9 } else { seed };
         Self { seed }
     }
 
@@ -111,6 +340,150 @@ impl RandomNumberGenerator {
     }
 }
 
+/// Mixes a seed and a position into a single value. The same inputs always give the same value,
+/// so random choices can be made about a position without depending on the order that positions
+/// are visited in.
+pub(crate) fn hash_coordinates(seed: u32, x: i32, y: i32, z: i32) -> u32 {
+    let mut hash = seed;
+    for value in [x, y, z] {
+        hash = (hash ^ value as u32)
+            .wrapping_mul(0x9e37_79b1)
+            .rotate_left(13);
+    }
+
+    // The finalizer from MurmurHash3, which makes every bit of the input affect every bit of the
+    // output.
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x85eb_ca6b);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0xc2b2_ae35);
+    hash ^= hash >> 16;
+    hash
+}
+
+/// Picks a unit vector on the x-z plane for a corner of a noise grid. Each corner's gradient
+/// depends only on the seed and the corner, so noise built from them never repeats.
+pub(crate) fn hash_gradient_2d(seed: u32, x: i64, z: i64) -> Vec2 {
+    let angle = hash_to_unit(hash_coordinates(seed, x as i32, 0, z as i32));
+    Vec2::from_angle(angle * std::f32::consts::TAU)
+}
+
+/// Picks a unit vector for a corner of a 3D noise grid, in the same way as `hash_gradient_2d`.
+pub(crate) fn hash_gradient_3d(seed: u32, x: i64, y: i64, z: i64) -> Vec3 {
+    let hash = hash_coordinates(seed, x as i32, y as i32, z as i32);
+
+    // Picking the height and the angle around the Y axis uniformly gives directions that are
+    // spread evenly over the sphere. Each is taken from half of the bits of the hash.
+    let y = hash_to_unit(hash >> 16 << 16) * 2.0 - 1.0;
+    let angle = hash_to_unit(hash << 16) * std::f32::consts::TAU;
+    let radius = (1.0 - y * y).sqrt();
+
+    Vec3(radius * angle.cos(), y, radius * angle.sin())
+}
+
+/// Maps a hash onto [0.0, 1.0).
+fn hash_to_unit(hash: u32) -> f32 {
+    (hash >> 8) as f32 / (1 << 24) as f32
+}
+
+/// Mixes a seed and a position on the x-z plane into a value within [0.0, 1.0).
+pub(crate) fn hash_position(seed: u32, x: i32, z: i32) -> f32 {
+    hash_to_unit(hash_coordinates(seed, x, 0, z))
+}
+
+pub(crate) fn interpolate(value_a: f32, value_b: f32, t: f32) -> f32 {
+    (1.0 - t) * value_a + t * value_b
+}
+
+/// Something that gives a smoothly varying value at every position on a plane, which can be used
+/// to shape terrain.
+pub(crate) trait NoiseSource: Send + Sync {
+    /// Returns the noise at the given position, which is within [-1.0, 1.0].
+    fn noise_at(&self, position: &Vec2) -> f32;
+}
+
+/// Skews a position so that the triangles of the simplex grid become half-squares.
+const SIMPLEX_SKEW: f32 = 0.366_025_42; // (sqrt(3) - 1) / 2
+/// Undoes `SIMPLEX_SKEW`.
+const SIMPLEX_UNSKEW: f32 = 0.211_324_87; // (3 - sqrt(3)) / 6
+
+/// Scales the sum of the corner contributions to fill [-1.0, 1.0].
+///
+/// Each corner contributes `(0.5 - d²)⁴ * (g · d)` for an offset `d` and a unit gradient `g`,
+/// which is at most `(0.5 - d²)⁴ * |d|`. Adding that up for all three corners of a triangle gives
+/// at most about 0.01008, so scaling by 99 keeps the noise within about ±0.998.
+const SIMPLEX_SCALE: f32 = 99.0;
+
+/// Two dimensional simplex noise.
+///
+/// Perlin noise blends the gradients at the four corners of a square grid cell, which lines the
+/// hills and valleys up along the X and Z axes. Simplex noise uses a grid of triangles instead,
+/// and each position is only influenced by the three corners of the triangle around it, which
+/// doesn't favor any direction.
+pub(crate) struct SimplexNoise {
+    seed: u32,
+    cell_size: f32,
+}
+
+impl SimplexNoise {
+    pub(crate) fn new(seed: u32, cell_size: f32) -> Self {
+        Self { seed, cell_size }
+    }
+
+    /// Returns the influence that the gradient at the given grid point has on a position, given
+    /// the offset from the grid point to the position.
+    fn value_from_corner(&self, xi: i64, zi: i64, offset: Vec2) -> f32 {
+        let falloff = 0.5 - offset.dot(&offset);
+
+        if falloff <= 0.0 {
+            return 0.0;
+        }
+
+        let gradient = hash_gradient_2d(self.seed, xi, zi);
+
+        falloff.powi(4) * gradient.dot(&offset)
+    }
+}
+
+impl NoiseSource for SimplexNoise {
+    fn noise_at(&self, position: &Vec2) -> f32 {
+        let Vec2(x, z) = *position / self.cell_size;
+
+        // Find the grid square containing the position in skewed space, along with the offset from
+        // its first corner in unskewed space.
+        let skew = (x + z) * SIMPLEX_SKEW;
+        let xi = (x + skew).floor();
+        let zi = (z + skew).floor();
+
+        let unskew = (xi + zi) * SIMPLEX_UNSKEW;
+        let offset = Vec2(x - (xi - unskew), z - (zi - unskew));
+
+        // The square is split into two triangles along its diagonal, and the middle corner of the
+        // triangle depends on which side of the diagonal the position is on.
+        let (middle_x, middle_z) = if offset.0 > offset.1 { (1, 0) } else { (0, 1) };
+
+        let (xi, zi) = (xi as i64, zi as i64);
+
+        let first = self.value_from_corner(xi, zi, offset);
+        let middle = self.value_from_corner(
+            xi + middle_x,
+            zi + middle_z,
+            offset
+                - Vec2(
+                    middle_x as f32 - SIMPLEX_UNSKEW,
+                    middle_z as f32 - SIMPLEX_UNSKEW,
+                ),
+        );
+        let last = self.value_from_corner(
+            xi + 1,
+            zi + 1,
+            offset - Vec2(1.0 - 2.0 * SIMPLEX_UNSKEW, 1.0 - 2.0 * SIMPLEX_UNSKEW),
+        );
+
+        (first + middle + last) * SIMPLEX_SCALE
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -149,6 +522,168 @@ mod tests {
         );
     }
 
+    /// Returns the frustum of a camera at `eye` looking along `direction`, with a 90° field of
+    /// view.
+    fn frustum(eye: Vec3, direction: Vec3, up: Vec3, aspect_ratio: f32) -> Frustum {
+        let projection = Mat4::perspective(FRAC_PI_2, aspect_ratio, 0.1, 100.0);
+        let view = Mat4::look_at(&eye, &(eye + direction), &up);
+        Frustum::from_view_projection(&(projection * view))
+    }
+
+    #[test]
+    fn frustum_contains_boxes_in_view() {
+        // Looking along the positive X axis, with a screen twice as wide as it is tall.
+        let frustum = frustum(
+            Vec3(0.0, 10.0, 0.0),
+            Vec3(1.0, 0.0, 0.0),
+            Vec3(0.0, 1.0, 0.0),
+            2.0,
+        );
+        let unit_box_at = |x: f32, y: f32, z: f32| {
+            frustum.intersects_box(&Vec3(x, y, z), &Vec3(x + 1.0, y + 1.0, z + 1.0))
+        };
+
+        assert!(unit_box_at(10.0, 10.0, 0.0));
+        assert!(unit_box_at(10.0, 14.0, 9.0));
+        // Boxes that are only partly in view.
+        assert!(unit_box_at(10.0, 10.0, 10.5));
+        assert!(unit_box_at(99.5, 10.0, 0.0));
+        assert!(unit_box_at(-0.5, 9.5, -0.5));
+
+        // Behind the camera, off to the sides, and beyond the far plane.
+        assert!(!unit_box_at(-10.0, 10.0, 0.0));
+        assert!(!unit_box_at(10.0, 10.0, 12.0));
+        assert!(!unit_box_at(10.0, 10.0, -13.0));
+        assert!(!unit_box_at(10.0, 17.0, 0.0));
+        assert!(!unit_box_at(10.0, 3.0, 0.0));
+        assert!(!unit_box_at(101.0, 10.0, 0.0));
+    }
+
+    #[test]
+    fn frustum_follows_the_camera_direction() {
+        // Looking straight down, with the top of the screen towards the positive Z axis.
+        let frustum = frustum(
+            Vec3(0.0, 0.0, 0.0),
+            Vec3(0.0, -1.0, 0.0),
+            Vec3(0.0, 0.0, 1.0),
+            1.0,
+        );
+        let box_around = |x: f32, y: f32, z: f32| {
+            frustum.intersects_box(
+                &Vec3(x - 1.0, y - 1.0, z - 1.0),
+                &Vec3(x + 1.0, y + 1.0, z + 1.0),
+            )
+        };
+
+        assert!(box_around(0.0, -20.0, 0.0));
+        assert!(!box_around(0.0, 20.0, 0.0));
+        assert!(!box_around(0.0, 0.0, 20.0));
+    }
+
+    /// Transforms the point by the matrix, dividing by its W coordinate.
+    fn transform_point(matrix: &Mat4, Vec3(x, y, z): Vec3) -> Vec3 {
+        let [x, y, z, w] = matrix
+            .0
+            .map(|row| row[0] * x + row[1] * y + row[2] * z + row[3]);
+        Vec3(x / w, y / w, z / w)
+    }
+
+    fn assert_mat4s_equal(a: &Mat4, b: &Mat4) {
+        const TOLERANCE: f32 = 0.0001;
+        let mat4s_equal = (0..4).all(|row| {
+            (0..4).all(|column| (a.0[row][column] - b.0[row][column]).abs() < TOLERANCE)
+        });
+        assert!(
+            mat4s_equal,
+            "matrices are not equal:\n  left: {:?}\n right: {:?}\n",
+            a, b
+        );
+    }
+
+    #[test]
+    fn look_at_moves_the_camera_to_the_origin() {
+        let eye = Vec3(1.0, 2.0, 3.0);
+        let view = Mat4::look_at(&eye, &Vec3(1.0, 2.0, 8.0), &Vec3(0.0, 1.0, 0.0));
+
+        assert_vec3s_equal(&transform_point(&view, eye), &Vec3(0.0, 0.0, 0.0));
+        assert_vec3s_equal(
+            &transform_point(&view, Vec3(1.0, 2.0, 8.0)),
+            &Vec3(0.0, 0.0, 5.0),
+        );
+        assert_vec3s_equal(
+            &transform_point(&view, Vec3(2.0, 3.0, 3.0)),
+            &Vec3(1.0, 1.0, 0.0),
+        );
+
+        // Looking along the positive X axis turns the positive Z axis to the camera's left.
+        let view = Mat4::look_at(&eye, &Vec3(5.0, 2.0, 3.0), &Vec3(0.0, 1.0, 0.0));
+        assert_vec3s_equal(
+            &transform_point(&view, Vec3(1.0, 2.0, 4.0)),
+            &Vec3(-1.0, 0.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn perspective_maps_the_view_onto_the_screen() {
+        let projection = Mat4::perspective(FRAC_PI_2, 2.0, 0.1, 100.0);
+
+        assert_vec3s_equal(
+            &transform_point(&projection, Vec3(0.0, 0.0, 0.1)),
+            &Vec3(0.0, 0.0, -1.0),
+        );
+        assert_vec3s_equal(
+            &transform_point(&projection, Vec3(0.0, 0.0, 100.0)),
+            &Vec3(0.0, 0.0, 1.0),
+        );
+
+        // The edges of the screen, which is half as tall as it is wide.
+        let Vec3(x, y, _) = transform_point(&projection, Vec3(10.0, 5.0, 10.0));
+        assert!((x - 1.0).abs() < 0.00001 && (y - 1.0).abs() < 0.00001);
+    }
+
+    #[test]
+    fn matrices_are_transposed_and_inverted() {
+        let matrix = Mat4([
+            [1.0, 2.0, 3.0, 4.0],
+            [0.0, 1.0, 5.0, 6.0],
+            [7.0, 0.0, 1.0, 8.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        let identity = Mat4([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+
+        assert_eq!(matrix.transpose().0[0], [1.0, 0.0, 7.0, 0.0]);
+        assert_eq!(matrix.transpose().transpose(), matrix);
+
+        let inverse = matrix.inverse().unwrap();
+        assert_mat4s_equal(&(matrix * inverse), &identity);
+        assert_mat4s_equal(&(inverse * matrix), &identity);
+
+        let view_projection = Mat4::perspective(1.2, 1.5, 0.1, 100.0)
+            * Mat4::look_at(
+                &Vec3(3.0, 4.0, 5.0),
+                &Vec3(0.0, 0.0, 0.0),
+                &Vec3(0.0, 1.0, 0.0),
+            );
+        let point = Vec3(1.0, -2.0, 0.5);
+        let inverse = view_projection.inverse().unwrap();
+        let round_trip = transform_point(&inverse, transform_point(&view_projection, point));
+        // Depth is squeezed up against the far plane by the projection, which loses precision.
+        assert!((round_trip - point).length() < 0.001, "{:?}", round_trip);
+
+        let flattened = Mat4([
+            [1.0, 2.0, 0.0, 0.0],
+            [2.0, 4.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        assert_eq!(flattened.inverse(), None);
+    }
+
     #[test]
     fn check_distribution_of_random_f32s() {
         const NUM_EXAMPLES: u32 = 10_000;
@@ -170,6 +705,34 @@ mod tests {
         }
     }
 
+    #[test]
+    fn check_distribution_of_position_hashes() {
+        const NUM_BUCKETS: u32 = 10;
+        const EXPECTED_BUCKET_COUNT: u32 = 100 * 100 / NUM_BUCKETS;
+        const MAX_ALLOWED_DEVIATION: u32 = 100;
+
+        let mut buckets = [0; NUM_BUCKETS as usize];
+
+        // Neighboring positions are where a poor hash would show patterns.
+        for x in -50..50 {
+            for z in -50..50 {
+                let value = hash_position(314159, x, z);
+                assert_eq!(value, hash_position(314159, x, z));
+
+                let i = (value / 0.1) as usize;
+                buckets[i] += 1;
+            }
+        }
+
+        for count in buckets.iter() {
+            assert!((*count - EXPECTED_BUCKET_COUNT as i32).unsigned_abs() < MAX_ALLOWED_DEVIATION);
+        }
+
+        assert_ne!(hash_position(1, 5, 9), hash_position(2, 5, 9));
+        assert_ne!(hash_position(1, 5, 9), hash_position(1, 9, 5));
+        assert_ne!(hash_coordinates(1, 5, 0, 9), hash_coordinates(1, 5, 1, 9));
+    }
+
     #[test]
     fn create_vec2_from_angle() {
         let examples = [
@@ -195,8 +758,78 @@ mod tests {
             a, b
         );
     }
-}
 
-pub(crate) fn interpolate(value_a: f32, value_b: f32, t: f32) -> f32 {
-    (1.0 - t) * value_a + t * value_b
+    #[test]
+    fn simplex_noise_is_the_same_for_the_same_seed() {
+        // Recorded from known seeds, so that changes to the noise (which would change the terrain
+        // of every world using it) are noticed.
+        let examples = [
+            (32131, Vec2(0.3, 0.7), -0.282145),
+            (32131, Vec2(-12.25, 4.5), 0.712499),
+            (32131, Vec2(100.1, -37.9), 0.402941),
+            (7, Vec2(0.3, 0.7), 0.311915),
+            (7, Vec2(-12.25, 4.5), 0.702509),
+            (7, Vec2(100.1, -37.9), -0.610487),
+        ];
+
+        for (seed, position, expected) in examples.into_iter() {
+            let noise = SimplexNoise::new(seed, 1.0);
+            assert!((noise.noise_at(&position) - expected).abs() < 0.00001);
+        }
+    }
+
+    #[test]
+    fn simplex_noise_is_zero_at_grid_points() {
+        let noise = SimplexNoise::new(32131, 4.0);
+
+        for (xi, zi) in [(0.0, 0.0), (3.0, -2.0), (-10.0, 7.0)] {
+            // Unskew the grid point to find where it is in the plane.
+            let unskew = (xi + zi) * SIMPLEX_UNSKEW;
+            let position = Vec2(xi - unskew, zi - unskew) / 0.25;
+
+            assert!(noise.noise_at(&position).abs() < 0.00001);
+        }
+    }
+
+    #[test]
+    fn simplex_scale_keeps_the_largest_possible_value_within_range() {
+        // The corners of the lower triangle of a skewed grid square, in the plane.
+        let corners = [
+            Vec2(0.0, 0.0),
+            Vec2(1.0 - SIMPLEX_UNSKEW, -SIMPLEX_UNSKEW),
+            Vec2(1.0 - 2.0 * SIMPLEX_UNSKEW, 1.0 - 2.0 * SIMPLEX_UNSKEW),
+        ];
+
+        // The largest value at each position, when every gradient points straight at it.
+        let mut largest: f32 = 0.0;
+        for i in 0..=500 {
+            for j in 0..=500 {
+                let position = Vec2(i as f32 / 500.0, j as f32 / 500.0 - 0.25);
+                let value: f32 = corners
+                    .iter()
+                    .map(|corner| {
+                        let offset = position - *corner;
+                        let distance_squared = offset.dot(&offset);
+                        (0.5 - distance_squared).max(0.0).powi(4) * distance_squared.sqrt()
+                    })
+                    .sum();
+                largest = largest.max(value * SIMPLEX_SCALE);
+            }
+        }
+
+        assert!(largest <= 1.0, "{}", largest);
+        assert!(largest > 0.99, "{}", largest);
+    }
+
+    #[test]
+    fn simplex_noise_stays_within_range() {
+        let noise = SimplexNoise::new(314159, 3.0);
+
+        for x in -200..200 {
+            for z in -200..200 {
+                let value = noise.noise_at(&Vec2(x as f32 * 0.37, z as f32 * 0.41));
+                assert!((-1.0..=1.0).contains(&value));
+            }
+        }
+    }
 }
@@ -0,0 +1,719 @@
+use crate::{
+    block::BlockId,
+    light::{LightChannel, MAX_LIGHT},
+    world::{ChunkNeighborhood, Coordinates, CHUNK_SIZE},
+};
+
+/// A single corner of a block face, laid out the way the cube shader expects it.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct Vertex {
+    pub(crate) position: [f32; 3],
+    pub(crate) normal: [f32; 3],
+    pub(crate) tex_coord: [f32; 2],
+    pub(crate) texture_layer: f32,
+    /// The sky light and block light shining on the face, from 0.0 (dark) to 1.0 (fully lit).
+    pub(crate) light: [f32; 2],
+    /// How open the corner is to the space in front of the face, from 0.0 (tucked into a corner
+    /// between blocks) to 1.0 (nothing around it).
+    pub(crate) ambient_occlusion: f32,
+}
+
+/// The triangles that make up the visible faces of a chunk's blocks.
+#[derive(Debug, Default)]
+pub(crate) struct ChunkMesh {
+    pub(crate) vertices: Vec<Vertex>,
+    /// The faces of translucent blocks, which are kept apart so that they can be drawn after
+    /// everything behind them.
+    pub(crate) translucent_vertices: Vec<Vertex>,
+}
+
+/// How brightly a face is lit, worked out from the blocks around it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct FaceLighting {
+    /// The sky light and block light in the block that the face looks out into.
+    light: [u8; 2],
+    /// The ambient occlusion of each corner of the face, in the order that `add_quad` lists the
+    /// corners, from 0 (most occluded) to `MAX_OCCLUSION_LEVEL` (not occluded at all).
+    ambient_occlusion: [u8; 4],
+}
+
+/// The ambient occlusion of a corner that has no blocks around it.
+const MAX_OCCLUSION_LEVEL: u8 = 3;
+
+/// The ways that a chunk's visible faces can be turned into triangles.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum MeshingMode {
+    /// Every visible face gets its own quad.
+    Naive,
+    /// Neighboring faces of the same type that lie in the same plane are merged into larger
+    /// rectangles, which greatly reduces the number of triangles on flat surfaces.
+    Greedy,
+}
+
+impl ChunkMesh {
+    /// Builds a mesh for the center chunk of the neighborhood. Only faces that are next to a
+    /// transparent block are included, so the size of the mesh scales with the surface area of
+    /// the terrain rather than its volume.
+    pub(crate) fn build(neighborhood: &ChunkNeighborhood, mode: MeshingMode) -> Self {
+        match mode {
+            MeshingMode::Naive => Self::build_naive(neighborhood),
+            MeshingMode::Greedy => Self::build_greedy(neighborhood),
+        }
+    }
+
+    fn build_naive(neighborhood: &ChunkNeighborhood) -> Self {
+        let mut mesh = Self::default();
+
+        for coordinates in neighborhood.center().block_coordinates() {
+            let block = neighborhood.get_block(&coordinates);
+
+            if block == BlockId::AIR {
+                continue;
+            }
+
+            for face in Face::ALL.iter() {
+                let neighbor = neighborhood.get_block(&face.neighbor(&coordinates));
+
+                if is_face_visible(block, neighbor) {
+                    let lighting = face_lighting(neighborhood, &coordinates, face);
+                    mesh.add_quad(&coordinates, face, block, lighting, 1, 1);
+                }
+            }
+        }
+
+        mesh
+    }
+
+    /// Works through the chunk one slice at a time for each direction that a face can point in.
+    /// The visible faces in the slice are collected into a grid, which is then covered by
+    /// rectangles of faces with matching blocks and lighting: each rectangle is grown as far as
+    /// it can go along the U axis first, and then along the V axis for as long as every face in
+    /// the next row matches.
+    fn build_greedy(neighborhood: &ChunkNeighborhood) -> Self {
+        const SLICE_AREA: usize = (CHUNK_SIZE * CHUNK_SIZE) as usize;
+
+        let mut mesh = Self::default();
+        let Coordinates(x0, y0, z0) = neighborhood.center().origin();
+        let index = |i: i32, j: i32| (j * CHUNK_SIZE + i) as usize;
+
+        for face in Face::ALL.iter() {
+            let u_axis = face.u_axis();
+            let v_axis = face.v_axis();
+            let u_index = axis_index(&u_axis);
+            let v_index = axis_index(&v_axis);
+            let normal_index = axis_index(&face.normal());
+
+            // Converts a depth through the slices and a position within a slice (measured along
+            // the positive U and V axes) back into world coordinates.
+            let coordinates_at = |depth: i32, i: i32, j: i32| {
+                let mut local = [0; 3];
+                local[normal_index] = depth;
+                local[u_index] = i;
+                local[v_index] = j;
+                Coordinates(x0 + local[0], y0 + local[1], z0 + local[2])
+            };
+
+            for depth in 0..CHUNK_SIZE {
+                let mut slice: Vec<Option<(BlockId, FaceLighting)>> = vec![None; SLICE_AREA];
+
+                for j in 0..CHUNK_SIZE {
+                    for i in 0..CHUNK_SIZE {
+                        let coordinates = coordinates_at(depth, i, j);
+                        let block = neighborhood.get_block(&coordinates);
+                        let neighbor = neighborhood.get_block(&face.neighbor(&coordinates));
+
+                        if block != BlockId::AIR && is_face_visible(block, neighbor) {
+                            let lighting = face_lighting(neighborhood, &coordinates, face);
+                            slice[index(i, j)] = Some((block, lighting));
+                        }
+                    }
+                }
+
+                for j in 0..CHUNK_SIZE {
+                    let mut i = 0;
+
+                    while i < CHUNK_SIZE {
+                        let face_type = match slice[index(i, j)] {
+                            Some(face_type) => face_type,
+                            None => {
+                                i += 1;
+                                continue;
+                            }
+                        };
+
+                        let mut width = 1;
+                        while i + width < CHUNK_SIZE
+                            && slice[index(i + width, j)] == Some(face_type)
+                        {
+                            width += 1;
+                        }
+
+                        let mut height = 1;
+                        while j + height < CHUNK_SIZE
+                            && (i..(i + width))
+                                .all(|k| slice[index(k, j + height)] == Some(face_type))
+                        {
+                            height += 1;
+                        }
+
+                        for covered_j in j..(j + height) {
+                            for covered_i in i..(i + width) {
+                                slice[index(covered_i, covered_j)] = None;
+                            }
+                        }
+
+                        // Quads are built outwards from the block holding the texture's origin,
+                        // which is on the far side of the rectangle for faces whose U or V axes
+                        // point in a negative direction.
+                        let start_i = if u_axis[u_index] > 0 {
+                            i
+                        } else {
+                            i + width - 1
+                        };
+                        let start_j = if v_axis[v_index] > 0 {
+                            j
+                        } else {
+                            j + height - 1
+                        };
+                        let start = coordinates_at(depth, start_i, start_j);
+
+                        let (block, lighting) = face_type;
+                        mesh.add_quad(&start, face, block, lighting, width, height);
+                        i += width;
+                    }
+                }
+            }
+        }
+
+        mesh
+    }
+
+    #[cfg(test)]
+    pub(crate) fn triangle_count(&self) -> usize {
+        (self.vertices.len() + self.translucent_vertices.len()) / 3
+    }
+
+    /// Adds a rectangle covering the given face of `width` blocks along the face's U axis and
+    /// `height` blocks along its V axis, starting from the block at `coordinates`. The texture
+    /// repeats once per block across the rectangle, and every face it covers has the same lighting.
+    fn add_quad(
+        &mut self,
+        coordinates: &Coordinates,
+        face: &Face,
+        block: BlockId,
+        lighting: FaceLighting,
+        width: i32,
+        height: i32,
+    ) {
+        let Coordinates(x, y, z) = *coordinates;
+        let [x0, y0, z0] = face.origin();
+        let [ux, uy, uz] = face.u_axis().map(|u| u * width);
+        let [vx, vy, vz] = face.v_axis().map(|v| v * height);
+        let (u, v) = (width as f32, height as f32);
+
+        let origin = [x + x0, y + y0, z + z0];
+        let corners = [
+            (origin, [0.0, 0.0]),
+            ([origin[0] + ux, origin[1] + uy, origin[2] + uz], [u, 0.0]),
+            (
+                [
+                    origin[0] + ux + vx,
+                    origin[1] + uy + vy,
+                    origin[2] + uz + vz,
+                ],
+                [u, v],
+            ),
+            ([origin[0] + vx, origin[1] + vy, origin[2] + vz], [0.0, v]),
+        ];
+
+        let normal = face.normal().map(|n| n as f32);
+        let texture_layer = face.texture_layer(block) as f32;
+        let light = lighting.light.map(|level| level as f32 / MAX_LIGHT as f32);
+        let vertices = if block.block_type().translucent {
+            &mut self.translucent_vertices
+        } else {
+            &mut self.vertices
+        };
+
+        // Two triangles per face, split along whichever diagonal joins the two least occluded
+        // corners. Splitting along the other diagonal would stretch the shadow of an occluded
+        // corner across the whole face.
+        let [a, b, c, d] = lighting.ambient_occlusion;
+        let indices = if a + c >= b + d {
+            [0, 1, 2, 2, 3, 0]
+        } else {
+            [1, 2, 3, 3, 0, 1]
+        };
+
+        for i in indices {
+            let (position, tex_coord) = corners[i];
+            let ambient_occlusion =
+                lighting.ambient_occlusion[i] as f32 / MAX_OCCLUSION_LEVEL as f32;
+
+            vertices.push(Vertex {
+                position: position.map(|p| p as f32),
+                normal,
+                tex_coord,
+                texture_layer,
+                light,
+                ambient_occlusion,
+            });
+        }
+    }
+}
+
+/// Works out the lighting of the given face of the block at `coordinates`. The face takes the
+/// light of the block it looks out into. Each of its corners is occluded by the two blocks along
+/// the edges that meet at the corner and the block diagonally across from it, out in front of
+/// the face, with a corner between two edge blocks being fully occluded whatever's across from it.
+fn face_lighting(
+    neighborhood: &ChunkNeighborhood,
+    coordinates: &Coordinates,
+    face: &Face,
+) -> FaceLighting {
+    let front = face.neighbor(coordinates);
+    let [u, v] = [face.u_axis(), face.v_axis()];
+
+    let is_occluding = |du: i32, dv: i32| {
+        let offset = [0, 1, 2].map(|axis| u[axis] * du + v[axis] * dv);
+        !neighborhood
+            .get_block(&front.offset(offset))
+            .block_type()
+            .transparent
+    };
+
+    // The corners go around the face in the same order as in `add_quad`.
+    let ambient_occlusion = [(-1, -1), (1, -1), (1, 1), (-1, 1)].map(|(du, dv)| {
+        let u_side = is_occluding(du, 0);
+        let v_side = is_occluding(0, dv);
+        let diagonal = is_occluding(du, dv);
+
+        if u_side && v_side {
+            0
+        } else {
+            MAX_OCCLUSION_LEVEL - (u_side as u8 + v_side as u8 + diagonal as u8)
+        }
+    });
+
+    FaceLighting {
+        light: [
+            neighborhood.get_light(&front, LightChannel::Sky),
+            neighborhood.get_light(&front, LightChannel::Block),
+        ],
+        ambient_occlusion,
+    }
+}
+
+/// Returns which of the X, Y or Z axes the unit vector lies along.
+fn axis_index(axis: &[i32; 3]) -> usize {
+    axis.iter().position(|a| *a != 0).unwrap()
+}
+
+/// A face can be seen if the block next to it is transparent. Faces between two blocks of the same
+/// type are hidden though, as are faces between two fluid blocks of any level, so the inside of a
+/// body of water isn't drawn.
+fn is_face_visible(block: BlockId, neighbor: BlockId) -> bool {
+    let both_fluids = block.fluid_level().is_some() && neighbor.fluid_level().is_some();
+    neighbor.block_type().transparent && neighbor != block && !both_fluids
+}
+
+/// One of the six sides of a block.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Face {
+    /// Facing towards negative Z.
+    Front,
+    /// Facing towards positive X.
+    Right,
+    /// Facing towards positive Z.
+    Far,
+    /// Facing towards negative X.
+    Left,
+    /// Facing towards positive Y.
+    Top,
+    /// Facing towards negative Y.
+    Bottom,
+}
+
+impl Face {
+    const ALL: [Face; 6] = [
+        Face::Front,
+        Face::Right,
+        Face::Far,
+        Face::Left,
+        Face::Top,
+        Face::Bottom,
+    ];
+
+    fn normal(&self) -> [i32; 3] {
+        match self {
+            Self::Front => [0, 0, -1],
+            Self::Right => [1, 0, 0],
+            Self::Far => [0, 0, 1],
+            Self::Left => [-1, 0, 0],
+            Self::Top => [0, 1, 0],
+            Self::Bottom => [0, -1, 0],
+        }
+    }
+
+    fn neighbor(&self, coordinates: &Coordinates) -> Coordinates {
+        let Coordinates(x, y, z) = *coordinates;
+        let [nx, ny, nz] = self.normal();
+        Coordinates(x + nx, y + ny, z + nz)
+    }
+
+    /// Returns the layer of the block texture array drawn on this face of the block.
+    fn texture_layer(&self, block: BlockId) -> u32 {
+        let textures = &block.block_type().textures;
+
+        match self {
+            Self::Top => textures.top,
+            Self::Bottom => textures.bottom,
+            Self::Front | Self::Right | Self::Far | Self::Left => textures.side,
+        }
+    }
+
+    /// The corner of the face that the texture's origin is mapped to, relative to the minimum
+    /// corner of the block.
+    fn origin(&self) -> [i32; 3] {
+        match self {
+            Self::Front => [0, 0, 0],
+            Self::Right => [1, 0, 0],
+            Self::Far => [1, 0, 1],
+            Self::Left => [0, 0, 1],
+            Self::Top => [0, 1, 0],
+            Self::Bottom => [0, 0, 1],
+        }
+    }
+
+    /// The direction along the face that the texture's U coordinate increases in.
+    fn u_axis(&self) -> [i32; 3] {
+        match self {
+            Self::Front => [1, 0, 0],
+            Self::Right => [0, 0, 1],
+            Self::Far => [-1, 0, 0],
+            Self::Left => [0, 0, -1],
+            Self::Top => [1, 0, 0],
+            Self::Bottom => [1, 0, 0],
+        }
+    }
+
+    /// The direction along the face that the texture's V coordinate increases in.
+    fn v_axis(&self) -> [i32; 3] {
+        match self {
+            Self::Front | Self::Right | Self::Far | Self::Left => [0, 1, 0],
+            Self::Top => [0, 0, 1],
+            Self::Bottom => [0, 0, -1],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::ChunkCoordinates;
+
+    const VERTICES_PER_FACE: usize = 6;
+
+    #[test]
+    fn single_block_has_every_face() {
+        let neighborhood = ChunkNeighborhood::with_blocks(
+            ChunkCoordinates(0, 0, 0),
+            &[(Coordinates(2, 3, 4), BlockId::STONE)],
+        );
+
+        let mesh = ChunkMesh::build(&neighborhood, MeshingMode::Naive);
+        assert_eq!(mesh.vertices.len(), 6 * VERTICES_PER_FACE);
+
+        let top_vertices: Vec<&Vertex> = mesh
+            .vertices
+            .iter()
+            .filter(|vertex| vertex.normal == [0.0, 1.0, 0.0])
+            .collect();
+
+        assert_eq!(top_vertices.len(), VERTICES_PER_FACE);
+        for vertex in top_vertices {
+            let [x, y, z] = vertex.position;
+            assert_eq!(y, 4.0);
+            assert!((2.0..=3.0).contains(&x));
+            assert!((4.0..=5.0).contains(&z));
+        }
+    }
+
+    #[test]
+    fn faces_between_solid_blocks_are_culled() {
+        // A solid 3x3x3 cube only has its outer surface drawn: 9 faces on each of the 6 sides.
+        let blocks: Vec<(Coordinates, BlockId)> = (0..27)
+            .map(|i| (Coordinates(i % 3, (i / 3) % 3, i / 9), BlockId::STONE))
+            .collect();
+        let neighborhood = ChunkNeighborhood::with_blocks(ChunkCoordinates(0, 0, 0), &blocks);
+
+        let mesh = ChunkMesh::build(&neighborhood, MeshingMode::Naive);
+        assert_eq!(mesh.vertices.len(), 9 * 6 * VERTICES_PER_FACE);
+        assert_eq!(mesh.triangle_count(), 9 * 6 * 2);
+    }
+
+    #[test]
+    fn faces_against_neighboring_chunks_are_culled() {
+        // The block at x = 16 belongs to the next chunk over, but still hides the face of the block
+        // at x = 15. Only the center chunk's blocks are added to the mesh.
+        let neighborhood = ChunkNeighborhood::with_blocks(
+            ChunkCoordinates(0, 0, 0),
+            &[
+                (Coordinates(15, 0, 0), BlockId::STONE),
+                (Coordinates(16, 0, 0), BlockId::STONE),
+            ],
+        );
+
+        let mesh = ChunkMesh::build(&neighborhood, MeshingMode::Naive);
+        assert_eq!(mesh.vertices.len(), 5 * VERTICES_PER_FACE);
+        assert!(!mesh
+            .vertices
+            .iter()
+            .any(|vertex| vertex.normal == [1.0, 0.0, 0.0]));
+    }
+
+    #[test]
+    fn transparent_blocks_show_faces_behind_them() {
+        let neighborhood = ChunkNeighborhood::with_blocks(
+            ChunkCoordinates(0, 0, 0),
+            &[
+                (Coordinates(0, 0, 0), BlockId::STONE),
+                (Coordinates(1, 0, 0), BlockId::WATER),
+                (Coordinates(2, 0, 0), BlockId::water(3)),
+            ],
+        );
+
+        // The stone keeps all 6 faces, since water is transparent. The first water block hides its
+        // faces against the stone and the flowing water, and the flowing water hides its face
+        // against the first. Water is translucent, so its faces are kept apart from the stone's.
+        let mesh = ChunkMesh::build(&neighborhood, MeshingMode::Naive);
+        assert_eq!(mesh.vertices.len(), 6 * VERTICES_PER_FACE);
+        assert_eq!(mesh.translucent_vertices.len(), (4 + 5) * VERTICES_PER_FACE);
+    }
+
+    #[test]
+    fn greedy_meshing_merges_flat_surfaces() {
+        // A flat 16x16 layer of grass covering the whole chunk, with a single stone block in the
+        // middle of it.
+        let mut blocks: Vec<(Coordinates, BlockId)> = (0..(CHUNK_SIZE * CHUNK_SIZE))
+            .map(|i| {
+                (
+                    Coordinates(i % CHUNK_SIZE, 0, i / CHUNK_SIZE),
+                    BlockId::GRASS,
+                )
+            })
+            .collect();
+        blocks[(8 * CHUNK_SIZE + 8) as usize].1 = BlockId::STONE;
+        let neighborhood = ChunkNeighborhood::with_blocks(ChunkCoordinates(0, 0, 0), &blocks);
+
+        let naive_mesh = ChunkMesh::build(&neighborhood, MeshingMode::Naive);
+        let greedy_mesh = ChunkMesh::build(&neighborhood, MeshingMode::Greedy);
+
+        assert_eq!(naive_mesh.triangle_count(), (2 * 16 * 16 + 4 * 16) * 2);
+        assert!(greedy_mesh.triangle_count() < naive_mesh.triangle_count() / 10);
+
+        // Both meshes have to cover exactly the same area, facing the same ways.
+        for normal in Face::ALL.iter().map(|face| face.normal().map(|n| n as f32)) {
+            assert_eq!(
+                covered_area(&naive_mesh, normal),
+                covered_area(&greedy_mesh, normal)
+            );
+        }
+
+        // The texture repeats once per block across each merged quad.
+        for vertex in greedy_mesh.vertices.iter() {
+            if vertex.normal == [0.0, 1.0, 0.0] && vertex.texture_layer == 2.0 {
+                let [u, v] = vertex.tex_coord;
+                assert!(u == 0.0 || u == u.round() && u <= 16.0);
+                assert!(v == 0.0 || v == v.round() && v <= 16.0);
+            }
+        }
+    }
+
+    #[test]
+    fn greedy_meshing_keeps_faces_in_place() {
+        // A lone block has nothing to merge with, so both kinds of meshing should produce the same
+        // quads. Checking every face catches any that are flipped or shifted by a block.
+        let neighborhood = ChunkNeighborhood::with_blocks(
+            ChunkCoordinates(-1, 0, 2),
+            &[(Coordinates(-7, 5, 40), BlockId::STONE)],
+        );
+
+        let naive_mesh = ChunkMesh::build(&neighborhood, MeshingMode::Naive);
+        let greedy_mesh = ChunkMesh::build(&neighborhood, MeshingMode::Greedy);
+
+        let mut naive_vertices = naive_mesh.vertices.clone();
+        let mut greedy_vertices = greedy_mesh.vertices.clone();
+        let by_position = |a: &Vertex, b: &Vertex| {
+            (a.normal, a.position, a.tex_coord)
+                .partial_cmp(&(b.normal, b.position, b.tex_coord))
+                .unwrap()
+        };
+        naive_vertices.sort_by(by_position);
+        greedy_vertices.sort_by(by_position);
+
+        assert_eq!(naive_vertices, greedy_vertices);
+    }
+
+    #[test]
+    fn greedy_meshing_only_merges_faces_with_the_same_light() {
+        // A flat layer of grass lit by sunlight, apart from a lamp's light over one corner.
+        let blocks: Vec<(Coordinates, BlockId)> = (0..(CHUNK_SIZE * CHUNK_SIZE))
+            .map(|i| {
+                (
+                    Coordinates(i % CHUNK_SIZE, 0, i / CHUNK_SIZE),
+                    BlockId::GRASS,
+                )
+            })
+            .collect();
+        let mut neighborhood = ChunkNeighborhood::with_blocks(ChunkCoordinates(0, 0, 0), &blocks);
+
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                let above = Coordinates(x, 1, z);
+                neighborhood.set_light(&above, LightChannel::Sky, MAX_LIGHT);
+
+                if x < 4 && z < 4 {
+                    neighborhood.set_light(&above, LightChannel::Block, 12);
+                }
+            }
+        }
+
+        let greedy_mesh = ChunkMesh::build(&neighborhood, MeshingMode::Greedy);
+        let top_vertices: Vec<&Vertex> = greedy_mesh
+            .vertices
+            .iter()
+            .filter(|vertex| vertex.normal == [0.0, 1.0, 0.0])
+            .collect();
+
+        // The lit corner is kept apart from the rest of the layer, which needs at least two more
+        // quads to cover it.
+        assert!(top_vertices.len() >= 3 * VERTICES_PER_FACE);
+        for vertex in top_vertices {
+            let [x, _, z] = vertex.position;
+            let lamp_light = if x <= 4.0 && z <= 4.0 { 0.8 } else { 0.0 };
+
+            assert_eq!(vertex.light[0], 1.0);
+            assert!(vertex.light[1] == lamp_light || vertex.light[1] == 0.0);
+        }
+
+        // Faces that look out into the dark are dark.
+        let bottom_vertex = greedy_mesh
+            .vertices
+            .iter()
+            .find(|vertex| vertex.normal == [0.0, -1.0, 0.0])
+            .unwrap();
+        assert_eq!(bottom_vertex.light, [0.0, 0.0]);
+    }
+
+    #[test]
+    fn corners_next_to_blocks_are_occluded() {
+        let neighborhood = ChunkNeighborhood::with_blocks(
+            ChunkCoordinates(0, 0, 0),
+            &[
+                (Coordinates(4, 4, 4), BlockId::STONE),
+                (Coordinates(5, 5, 4), BlockId::STONE),
+                (Coordinates(4, 5, 3), BlockId::STONE),
+                (Coordinates(3, 5, 5), BlockId::STONE),
+            ],
+        );
+
+        let mesh = ChunkMesh::build(&neighborhood, MeshingMode::Naive);
+        let top_vertices: Vec<&Vertex> = mesh
+            .vertices
+            .iter()
+            .filter(|vertex| vertex.normal == [0.0, 1.0, 0.0] && vertex.position[1] == 5.0)
+            .collect();
+        assert_eq!(top_vertices.len(), VERTICES_PER_FACE);
+
+        for vertex in top_vertices.iter() {
+            let expected = match vertex.position {
+                // Between the two blocks on either side of it.
+                [5.0, 5.0, 4.0] => 0.0,
+                // Next to a single block, or across from one.
+                [4.0, 5.0, 4.0] | [5.0, 5.0, 5.0] | [4.0, 5.0, 5.0] => 2.0 / 3.0,
+                position => panic!("unexpected vertex at {:?}", position),
+            };
+            assert_eq!(vertex.ambient_occlusion, expected);
+        }
+
+        // The face is split so that the fully occluded corner is only in one of the triangles.
+        let darkest_corners = top_vertices
+            .iter()
+            .filter(|vertex| vertex.ambient_occlusion == 0.0)
+            .count();
+        assert_eq!(darkest_corners, 1);
+    }
+
+    #[test]
+    fn quads_are_split_away_from_the_most_occluded_corner() {
+        // The block across from the first corner of the top face is the only one around it.
+        let neighborhood = ChunkNeighborhood::with_blocks(
+            ChunkCoordinates(0, 0, 0),
+            &[
+                (Coordinates(4, 4, 4), BlockId::STONE),
+                (Coordinates(3, 5, 3), BlockId::STONE),
+            ],
+        );
+
+        let mesh = ChunkMesh::build(&neighborhood, MeshingMode::Greedy);
+        let top_vertices: Vec<&Vertex> = mesh
+            .vertices
+            .iter()
+            .filter(|vertex| vertex.normal == [0.0, 1.0, 0.0] && vertex.position[1] == 5.0)
+            .collect();
+        assert_eq!(top_vertices.len(), VERTICES_PER_FACE);
+
+        let occluded_corners: Vec<&&Vertex> = top_vertices
+            .iter()
+            .filter(|vertex| vertex.ambient_occlusion < 1.0)
+            .collect();
+        assert_eq!(occluded_corners.len(), 1);
+        assert_eq!(occluded_corners[0].position, [4.0, 5.0, 4.0]);
+    }
+
+    #[test]
+    fn faces_use_the_textures_for_their_side_of_the_block() {
+        let neighborhood = ChunkNeighborhood::with_blocks(
+            ChunkCoordinates(0, 0, 0),
+            &[(Coordinates(2, 2, 2), BlockId::GRASS)],
+        );
+        let textures = &BlockId::GRASS.block_type().textures;
+
+        for mode in [MeshingMode::Naive, MeshingMode::Greedy] {
+            let mesh = ChunkMesh::build(&neighborhood, mode);
+
+            for vertex in mesh.vertices.iter() {
+                let expected = match vertex.normal {
+                    [0.0, 1.0, 0.0] => textures.top,
+                    [0.0, -1.0, 0.0] => textures.bottom,
+                    _ => textures.side,
+                };
+                assert_eq!(vertex.texture_layer, expected as f32);
+            }
+        }
+
+        assert_ne!(textures.top, textures.side);
+        assert_ne!(textures.side, textures.bottom);
+    }
+
+    /// Adds up the area of every triangle in the mesh that faces along the given normal.
+    fn covered_area(mesh: &ChunkMesh, normal: [f32; 3]) -> f32 {
+        mesh.vertices
+            .chunks(3)
+            .filter(|triangle| triangle[0].normal == normal)
+            .map(|triangle| {
+                let [a, b, c] = [0, 1, 2].map(|i| triangle[i].position);
+                let ab = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+                let ac = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+                let cross = [
+                    ab[1] * ac[2] - ab[2] * ac[1],
+                    ab[2] * ac[0] - ab[0] * ac[2],
+                    ab[0] * ac[1] - ab[1] * ac[0],
+                ];
+                (cross[0].powi(2) + cross[1].powi(2) + cross[2].powi(2)).sqrt() / 2.0
+            })
+            .sum()
+    }
+}
@@ -0,0 +1,252 @@
+use std::fmt;
+
+use crate::{
+    block::BlockId,
+    math::{self, RandomNumberGenerator},
+    terrain::TerrainGenerator,
+    world::{Chunk, ChunkCoordinates, ColumnCoordinates, Coordinates, CHUNK_SIZE},
+};
+
+/// Mixed into the seed of each chunk's ore veins, so that the veins don't line up with anything
+/// else generated from the seed.
+const ORE_SEED_SALT: u32 = 0x3c6e_f372;
+
+/// The number of y coordinates in each band of `OreStatistics`.
+const STATISTICS_BAND_HEIGHT: i32 = 8;
+
+/// The steps that a vein can take from one block to the next.
+const VEIN_STEPS: [[i32; 3]; 6] = [
+    [1, 0, 0],
+    [-1, 0, 0],
+    [0, 1, 0],
+    [0, -1, 0],
+    [0, 0, 1],
+    [0, 0, -1],
+];
+
+/// The rules for scattering veins of an ore through the stone.
+#[derive(Debug)]
+pub(crate) struct Ore {
+    pub(crate) block: BlockId,
+    /// The y coordinates of the lowest and highest blocks that the ore can replace.
+    min_y: i32,
+    max_y: i32,
+    /// The number of blocks in each vein. Veins end up smaller when they wander out of the stone.
+    vein_size: u32,
+    /// The average number of veins in each chunk within the ore's depth range. Below 1.0, most
+    /// chunks don't have a vein at all.
+    veins_per_chunk: f32,
+}
+
+/// Every ore, placed in this order.
+pub(crate) static ORES: [Ore; 4] = [
+    Ore {
+        block: BlockId::COAL_ORE,
+        min_y: 0,
+        max_y: 47,
+        vein_size: 10,
+        veins_per_chunk: 3.0,
+    },
+    Ore {
+        block: BlockId::IRON_ORE,
+        min_y: 0,
+        max_y: 31,
+        vein_size: 6,
+        veins_per_chunk: 1.5,
+    },
+    Ore {
+        block: BlockId::GOLD_ORE,
+        min_y: 0,
+        max_y: 15,
+        vein_size: 5,
+        veins_per_chunk: 0.5,
+    },
+    Ore {
+        block: BlockId::DIAMOND_ORE,
+        min_y: 0,
+        max_y: 7,
+        vein_size: 4,
+        veins_per_chunk: 0.25,
+    },
+];
+
+/// Replaces some of the stone in the chunk with veins of ore. Veins never leave the chunk they
+/// start in, so the ores in a chunk only depend on the seed and the chunk's coordinates.
+pub(crate) fn place_ores(seed: u32, chunk_coordinates: &ChunkCoordinates, chunk: &mut Chunk) {
+    let origin = chunk_coordinates.origin();
+    let Coordinates(_, chunk_bottom, _) = origin;
+    let ChunkCoordinates(chunk_x, chunk_y, chunk_z) = *chunk_coordinates;
+
+    for (i, ore) in ORES.iter().enumerate() {
+        let bottom = ore.min_y.max(chunk_bottom);
+        let top = ore.max_y.min(chunk_bottom + CHUNK_SIZE - 1);
+
+        if bottom > top {
+            continue;
+        }
+
+        // Every ore draws from its own generator, so changing the rules for one ore doesn't move
+        // the veins of the others.
+        let ore_seed = (seed ^ ORE_SEED_SALT).wrapping_add(i as u32);
+        let mut rng = RandomNumberGenerator::with_seed(math::hash_coordinates(
+            ore_seed, chunk_x, chunk_y, chunk_z,
+        ));
+
+        let extra_vein = rng.gen_f32() < ore.veins_per_chunk.fract();
+        let num_veins = ore.veins_per_chunk as u32 + extra_vein as u32;
+
+        for _ in 0..num_veins {
+            let mut coordinates = Coordinates(
+                origin.0 + rng.gen_range(0, CHUNK_SIZE as u32) as i32,
+                rng.gen_range(bottom as u32, top as u32 + 1) as i32,
+                origin.2 + rng.gen_range(0, CHUNK_SIZE as u32) as i32,
+            );
+
+            for _ in 0..ore.vein_size {
+                let Coordinates(_, y, _) = coordinates;
+
+                if coordinates.chunk() == *chunk_coordinates
+                    && (bottom..=top).contains(&y)
+                    && chunk.get_block(&coordinates) == BlockId::STONE
+                {
+                    chunk.set_block(&coordinates, ore.block);
+                }
+
+                let step = VEIN_STEPS[rng.gen_range(0, VEIN_STEPS.len() as u32) as usize];
+                coordinates = coordinates.offset(step);
+            }
+        }
+    }
+}
+
+/// Counts the ores in a square of generated columns, grouped into bands of y coordinates, so the
+/// ore rules can be tuned.
+pub(crate) struct OreStatistics {
+    num_columns: u32,
+    /// The number of blocks of each ore in each band, in the same order as `ORES`, along with the
+    /// number of stone blocks left in the band.
+    bands: Vec<([u32; ORES.len()], u32)>,
+}
+
+impl OreStatistics {
+    /// Generates a square of `size` by `size` columns starting at the origin and counts the blocks
+    /// in them.
+    pub(crate) fn collect(generator: &TerrainGenerator, size: u32) -> Self {
+        let num_bands = generator.chunks_per_column() * CHUNK_SIZE / STATISTICS_BAND_HEIGHT;
+        let mut bands = vec![([0; ORES.len()], 0); num_bands as usize];
+
+        for column_x in 0..size as i32 {
+            for column_z in 0..size as i32 {
+                let column = ColumnCoordinates(column_x, column_z);
+
+                for (chunk_coordinates, chunk) in generator.generate_column(&column) {
+                    for coordinates in chunk_coordinates.block_coordinates() {
+                        let block = chunk.get_block(&coordinates);
+                        let (ore_counts, stone_count) =
+                            &mut bands[(coordinates.1 / STATISTICS_BAND_HEIGHT) as usize];
+
+                        if block == BlockId::STONE {
+                            *stone_count += 1;
+                        } else if let Some(i) = ORES.iter().position(|ore| ore.block == block) {
+                            ore_counts[i] += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        Self {
+            num_columns: size * size,
+            bands,
+        }
+    }
+
+    /// Returns the number of blocks of the ore at the given index in `ORES` between the given y
+    /// coordinates, which must line up with the bands.
+    #[cfg(test)]
+    fn count_between(&self, ore_index: usize, bottom: i32, top: i32) -> u32 {
+        let first_band = (bottom / STATISTICS_BAND_HEIGHT) as usize;
+        let last_band = (top / STATISTICS_BAND_HEIGHT) as usize;
+
+        self.bands[first_band..=last_band]
+            .iter()
+            .map(|(ore_counts, _)| ore_counts[ore_index])
+            .sum()
+    }
+}
+
+impl fmt::Display for OreStatistics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Ore blocks in {} columns", self.num_columns)?;
+
+        write!(f, "{:>9}", "y")?;
+        for ore in ORES.iter() {
+            write!(f, "{:>13}", ore.block.block_type().name)?;
+        }
+        writeln!(f, "{:>13}", "stone")?;
+
+        // Listed from the top down, the same way the bands are stacked in the world.
+        for (i, (ore_counts, stone_count)) in self.bands.iter().enumerate().rev() {
+            let bottom = i as i32 * STATISTICS_BAND_HEIGHT;
+            let top = bottom + STATISTICS_BAND_HEIGHT - 1;
+            write!(f, "{:>9}", format!("{}-{}", bottom, top))?;
+
+            for count in ore_counts.iter() {
+                write!(f, "{:>13}", count)?;
+            }
+            writeln!(f, "{:>13}", stone_count)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::TerrainOptions;
+
+    #[test]
+    fn ores_only_replace_stone() {
+        let chunk_coordinates = ChunkCoordinates(3, 0, -2);
+
+        let mut dirt_chunk = Chunk::empty();
+        let mut stone_chunk = Chunk::empty();
+        for coordinates in chunk_coordinates.block_coordinates() {
+            dirt_chunk.set_block(&coordinates, BlockId::DIRT);
+            stone_chunk.set_block(&coordinates, BlockId::STONE);
+        }
+
+        place_ores(32131, &chunk_coordinates, &mut dirt_chunk);
+        place_ores(32131, &chunk_coordinates, &mut stone_chunk);
+
+        let num_ores = chunk_coordinates
+            .block_coordinates()
+            .filter(|coordinates| {
+                assert_eq!(dirt_chunk.get_block(coordinates), BlockId::DIRT);
+                stone_chunk.get_block(coordinates) != BlockId::STONE
+            })
+            .count();
+
+        assert!(num_ores > 0);
+    }
+
+    #[test]
+    fn ores_stay_within_their_depth_ranges() {
+        let generator = TerrainGenerator::new(TerrainOptions::default());
+        let statistics = OreStatistics::collect(&generator, 6);
+        let world_top = generator.chunks_per_column() * CHUNK_SIZE - 1;
+
+        for (i, ore) in ORES.iter().enumerate() {
+            let top = ore.max_y.min(world_top);
+
+            assert!(statistics.count_between(i, ore.min_y, top) > 0);
+            assert_eq!(statistics.count_between(i, top + 1, world_top), 0);
+        }
+
+        // Every band is listed, along with a header for each ore.
+        let report = statistics.to_string();
+        assert_eq!(report.lines().count(), 2 + statistics.bands.len());
+        assert!(report.contains("diamond ore"));
+    }
+}
@@ -0,0 +1,163 @@
+use crate::{math::Vec3, world::Coordinates};
+
+/// How close two boxes can get before they are considered to be touching. Without this, rounding
+/// errors would leave a box resting on the ground slightly inside of it, and it would snag on the
+/// edges of the blocks that it slides across.
+const EPSILON: f32 = 0.0001;
+
+/// An axis-aligned box, used for the parts of the world that are not made out of blocks.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct Aabb {
+    min: [f32; 3],
+    max: [f32; 3],
+}
+
+impl Aabb {
+    pub(crate) fn new(min: Vec3, max: Vec3) -> Self {
+        Self {
+            min: [min.x(), min.y(), min.z()],
+            max: [max.x(), max.y(), max.z()],
+        }
+    }
+
+    /// Returns the box taken up by the block at the given coordinates.
+    pub(crate) fn block(coordinates: &Coordinates) -> Self {
+        let Coordinates(x, y, z) = *coordinates;
+        let min = [x as f32, y as f32, z as f32];
+
+        Self {
+            min,
+            max: [min[0] + 1.0, min[1] + 1.0, min[2] + 1.0],
+        }
+    }
+
+    /// Whether the two boxes overlap. Boxes that only touch along an edge or face don't count.
+    pub(crate) fn intersects(&self, other: &Aabb) -> bool {
+        (0..3).all(|axis| {
+            self.min[axis] < other.max[axis] - EPSILON && other.min[axis] < self.max[axis] - EPSILON
+        })
+    }
+
+    /// Moves the box by `movement`, one axis at a time, stopping it when it runs into a block
+    /// that `is_solid` returns true for. Returns how far the box actually moved.
+    ///
+    /// Every block between the start and end of the move is checked, so the box can't pass
+    /// through blocks however far it moves in one step.
+    pub(crate) fn sweep(&self, movement: &Vec3, is_solid: impl Fn(&Coordinates) -> bool) -> Vec3 {
+        let mut aabb = *self;
+        let mut moved = [0.0; 3];
+
+        // Moving vertically first means that a box falling onto the ground lands before it tries
+        // to move sideways, so it can't catch on the side of the block it's landing on.
+        for axis in [1, 0, 2] {
+            let distance = [movement.x(), movement.y(), movement.z()][axis];
+            moved[axis] = aabb.sweep_axis(axis, distance, &is_solid);
+            aabb.min[axis] += moved[axis];
+            aabb.max[axis] += moved[axis];
+        }
+
+        Vec3(moved[0], moved[1], moved[2])
+    }
+
+    fn sweep_axis(
+        &self,
+        axis: usize,
+        distance: f32,
+        is_solid: &impl Fn(&Coordinates) -> bool,
+    ) -> f32 {
+        if distance == 0.0 {
+            return 0.0;
+        }
+
+        // The space covered by the box over the course of the move.
+        let mut swept = *self;
+        if distance > 0.0 {
+            swept.max[axis] += distance;
+        } else {
+            swept.min[axis] += distance;
+        }
+
+        let mut allowed_distance = distance;
+
+        for coordinates in swept.overlapping_blocks() {
+            if !is_solid(&coordinates) {
+                continue;
+            }
+
+            let block = Aabb::block(&coordinates);
+
+            // Blocks that the box already overlaps are ignored, so that a box that ends up inside
+            // of a block can still move out of it.
+            if distance > 0.0 && block.min[axis] >= self.max[axis] - EPSILON {
+                allowed_distance = allowed_distance.min(block.min[axis] - self.max[axis]);
+            } else if distance < 0.0 && block.max[axis] <= self.min[axis] + EPSILON {
+                allowed_distance = allowed_distance.max(block.max[axis] - self.min[axis]);
+            }
+        }
+
+        allowed_distance
+    }
+
+    /// Iterates over the coordinates of every block that the box overlaps.
+    fn overlapping_blocks(&self) -> impl Iterator<Item = Coordinates> {
+        let min = self.min.map(|value| (value + EPSILON).floor() as i32);
+        let max = self.max.map(|value| (value - EPSILON).ceil() as i32);
+
+        (min[1]..max[1]).flat_map(move |y| {
+            (min[2]..max[2]).flat_map(move |z| (min[0]..max[0]).map(move |x| Coordinates(x, y, z)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A floor of blocks along y = 0, with a wall along x = 3.
+    fn is_solid(coordinates: &Coordinates) -> bool {
+        let Coordinates(x, y, _) = *coordinates;
+        y == 0 || x == 3
+    }
+
+    #[test]
+    fn falling_boxes_land_on_the_floor() {
+        let aabb = Aabb::new(Vec3(0.2, 2.5, 0.2), Vec3(0.8, 4.0, 0.8));
+
+        // Falling far enough in a single step to pass all the way through the floor still stops
+        // on top of it.
+        for fall_distance in [0.5_f32, 1.5, 10.0] {
+            let moved = aabb.sweep(&Vec3(0.0, -fall_distance, 0.0), is_solid);
+            assert!((moved.y() + fall_distance.min(1.5)).abs() < 0.0001);
+        }
+    }
+
+    #[test]
+    fn boxes_slide_along_walls() {
+        let aabb = Aabb::new(Vec3(1.2, 1.0, 0.2), Vec3(1.8, 2.5, 0.8));
+
+        // The part of the move into the wall is stopped, but the rest of the move goes ahead.
+        let moved = aabb.sweep(&Vec3(2.0, -0.5, 1.0), is_solid);
+        assert!((moved.x() - 1.2).abs() < 0.0001);
+        assert_eq!(moved.y(), 0.0);
+        assert_eq!(moved.z(), 1.0);
+    }
+
+    #[test]
+    fn boxes_resting_on_the_floor_move_freely() {
+        let aabb = Aabb::new(Vec3(0.2, 1.0 - EPSILON / 2.0, 0.2), Vec3(0.8, 2.5, 0.8));
+
+        // Being slightly inside of the floor doesn't stop the box from moving across it.
+        let moved = aabb.sweep(&Vec3(-5.0, 0.0, 7.0), is_solid);
+        assert_eq!(moved.x(), -5.0);
+        assert_eq!(moved.z(), 7.0);
+    }
+
+    #[test]
+    fn touching_boxes_do_not_intersect() {
+        let block = Aabb::block(&Coordinates(0, 0, 0));
+
+        assert!(block.intersects(&Aabb::new(Vec3(0.5, 0.5, 0.5), Vec3(2.0, 2.0, 2.0))));
+        assert!(!block.intersects(&Aabb::new(Vec3(0.5, 1.0, 0.5), Vec3(2.0, 2.0, 2.0))));
+        assert!(!block.intersects(&Aabb::block(&Coordinates(-1, 0, 0))));
+    }
+}
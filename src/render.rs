@@ -21,8 +21,11 @@ use winit::{
 };
 
 use crate::{
-    math::{RandomNumberGenerator, Vec3},
-    world::Camera,
+    block::NUM_BLOCK_TEXTURES,
+    math::{Frustum, Mat4, RandomNumberGenerator, Vec3},
+    mesh::Vertex,
+    resource::TextureArray,
+    world::{Camera, ChunkCoordinates, ChunkUpdate, Coordinates, CHUNK_SIZE},
 };
 
 const CUBE_VERTEX_SHADER_SRC: &str = include_str!("../shaders/cube.vert");
@@ -30,22 +33,44 @@ const CUBE_FRAGMENT_SHADER_SRC: &str = include_str!("../shaders/cube.frag");
 const SKYBOX_VERTEX_SHADER_SRC: &str = include_str!("../shaders/skybox.vert");
 const SKYBOX_FRAGMENT_SHADER_SRC: &str = include_str!("../shaders/skybox.frag");
 
+/// The distances from the camera to the nearest and furthest points that are drawn.
+const NEAR: f32 = 0.1;
+const FAR: f32 = 10000.0;
+
+/// How much of the color of translucent blocks is drawn over the blocks behind them, from 0.0
+/// (invisible) to 1.0 (opaque).
+const TRANSLUCENT_OPACITY: f32 = 0.6;
+
 pub(crate) struct Renderer {
     window: Window,
     context: PossiblyCurrentContext,
     surface: Surface<WindowSurface>,
     cube_program: Program,
-    cube_vertex_array_id: GLuint,
     cube_texture_id: GLuint,
-    position_array_buffer_id: GLuint,
-    cube_count: usize,
+    chunk_buffers: HashMap<ChunkCoordinates, ChunkBuffers>,
+    translucent_chunk_buffers: HashMap<ChunkCoordinates, ChunkBuffers>,
+    /// Where the camera was when it was last set, used to sort the translucent faces.
+    camera_position: Vec3,
+    /// What the camera could see when it was last set, used to skip chunks that are out of view.
+    camera_frustum: Frustum,
+    /// The angle between the left and right edges of the screen, in radians.
+    field_of_view: f32,
+    aspect_ratio: f32,
+    statistics: DrawStatistics,
+    ambient_occlusion: bool,
     skybox_program: Program,
     skybox_vertex_array_id: GLuint,
     skybox_texture_id: GLuint,
 }
 
 impl Renderer {
-    pub(crate) fn new(event_loop: &EventLoop<()>, windowed: bool, disable_vsync: bool) -> Self {
+    pub(crate) fn new(
+        event_loop: &EventLoop<()>,
+        windowed: bool,
+        disable_vsync: bool,
+        field_of_view: f32,
+        block_textures: &TextureArray,
+    ) -> Self {
         let fullscreen_option = if windowed {
             None
         } else {
@@ -104,63 +129,56 @@ impl Renderer {
             gl::DepthFunc(gl::LEQUAL);
         }
 
-        let cube_vertex_array_id = unsafe {
-            let mut cube_vertex_array_id = 0;
-            gl::GenVertexArrays(1, &mut cube_vertex_array_id);
-            gl::BindVertexArray(cube_vertex_array_id);
-            cube_vertex_array_id
-        };
-
-        let position_array_buffer_id = unsafe {
-            let mut position_array_id = 0;
-            gl::GenBuffers(1, &mut position_array_id);
-            gl::BindBuffer(gl::ARRAY_BUFFER, position_array_id);
-            gl::EnableVertexAttribArray(0);
-            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, 0, std::ptr::null());
-            gl::VertexAttribDivisor(0, 1);
-            position_array_id
-        };
-
         let cube_texture_id = unsafe {
             let mut cube_texture_id = 0;
             gl::GenTextures(1, &mut cube_texture_id);
-            gl::BindTexture(gl::TEXTURE_2D, cube_texture_id);
-
-            const TEXTURE_WIDTH: usize = 8;
-            const TEXTURE_HEIGHT: usize = 8;
-            const NUM_PIXELS: usize = TEXTURE_WIDTH * TEXTURE_HEIGHT;
-            const VALUES_PER_PIXEL: usize = 3;
-            const TEXTURE_SIZE: usize = NUM_PIXELS * VALUES_PER_PIXEL;
-
-            let mut rng = RandomNumberGenerator::with_seed(42);
-            let mut texture: Vec<u8> = Vec::with_capacity(TEXTURE_SIZE);
-
-            for _ in 0..NUM_PIXELS {
-                let value = rng.gen_range(50, 200) as u8;
-                texture.push(value);
-                texture.push(value);
-                texture.push(value);
-            }
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, cube_texture_id);
 
-            gl::TexImage2D(
-                gl::TEXTURE_2D,
+            // Rows of very small textures aren't a multiple of four bytes long.
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TexImage3D(
+                gl::TEXTURE_2D_ARRAY,
                 0,
                 gl::RGB8 as GLint,
-                TEXTURE_WIDTH as GLsizei,
-                TEXTURE_HEIGHT as GLsizei,
+                block_textures.size as GLsizei,
+                block_textures.size as GLsizei,
+                NUM_BLOCK_TEXTURES as GLsizei,
                 0,
                 gl::RGB,
                 gl::UNSIGNED_BYTE,
-                texture.as_ptr() as *const c_void,
+                block_textures.pixels.as_ptr() as *const c_void,
+            );
+            gl::TexParameteri(
+                gl::TEXTURE_2D_ARRAY,
+                gl::TEXTURE_MAG_FILTER,
+                gl::NEAREST as GLint,
             );
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as GLint);
             gl::TexParameteri(
-                gl::TEXTURE_2D,
+                gl::TEXTURE_2D_ARRAY,
                 gl::TEXTURE_MIN_FILTER,
-                gl::NEAREST_MIPMAP_NEAREST as GLint,
+                gl::NEAREST_MIPMAP_LINEAR as GLint,
+            );
+
+            // Greedy meshing stretches a texture across several blocks, so it repeats. Each
+            // texture is its own layer of the array and is mipmapped separately, so unlike tiles
+            // in an atlas, neighboring textures never bleed into each other.
+            gl::TexParameteri(
+                gl::TEXTURE_2D_ARRAY,
+                gl::TEXTURE_WRAP_S,
+                gl::REPEAT as GLint,
+            );
+            gl::TexParameteri(
+                gl::TEXTURE_2D_ARRAY,
+                gl::TEXTURE_WRAP_T,
+                gl::REPEAT as GLint,
+            );
+            gl::TexParameteri(
+                gl::TEXTURE_2D_ARRAY,
+                gl::TEXTURE_MAX_LEVEL,
+                block_textures.size.ilog2() as GLint,
             );
 
-            gl::GenerateMipmap(gl::TEXTURE_2D);
+            gl::GenerateMipmap(gl::TEXTURE_2D_ARRAY);
             cube_texture_id
         };
 
@@ -230,10 +248,20 @@ impl Renderer {
             surface,
             context,
             cube_program,
-            cube_vertex_array_id,
             cube_texture_id,
-            position_array_buffer_id,
-            cube_count: 0,
+            chunk_buffers: HashMap::new(),
+            translucent_chunk_buffers: HashMap::new(),
+            camera_position: Vec3(0.0, 0.0, 0.0),
+            camera_frustum: Frustum::from_view_projection(&Mat4::perspective(
+                field_of_view,
+                1.0,
+                NEAR,
+                FAR,
+            )),
+            field_of_view,
+            aspect_ratio: 1.0,
+            statistics: DrawStatistics::default(),
+            ambient_occlusion: true,
             skybox_program,
             skybox_vertex_array_id,
             skybox_texture_id,
@@ -282,6 +310,8 @@ impl Renderer {
     }
 
     pub(crate) fn clear(&mut self) {
+        self.statistics = DrawStatistics::default();
+
         unsafe {
             gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
         }
@@ -290,10 +320,18 @@ impl Renderer {
     pub(crate) fn draw_cubes(&mut self) {
         self.activate_cube_program();
 
-        unsafe {
-            gl::BindVertexArray(self.cube_vertex_array_id);
-            gl::BindTexture(gl::TEXTURE_2D, self.cube_texture_id);
-            gl::DrawArraysInstanced(gl::TRIANGLES, 0, 36, self.cube_count as GLint);
+        for (chunk_coordinates, buffers) in self.chunk_buffers.iter() {
+            if !self
+                .statistics
+                .count_chunk(&self.camera_frustum, chunk_coordinates, buffers, false)
+            {
+                continue;
+            }
+
+            unsafe {
+                gl::BindVertexArray(buffers.vertex_array_id);
+                gl::DrawArrays(gl::TRIANGLES, 0, buffers.vertex_count as GLint);
+            }
         }
     }
 
@@ -303,26 +341,113 @@ impl Renderer {
         unsafe {
             gl::BindVertexArray(self.skybox_vertex_array_id);
             gl::BindTexture(gl::TEXTURE_CUBE_MAP, self.skybox_texture_id);
-            gl::DrawArrays(gl::TRIANGLES, 0, 36);
+            gl::DrawArrays(gl::TRIANGLES, 0, 3);
         }
     }
 
-    pub(crate) fn update_block_cache(&mut self, positions: impl Iterator<Item = Vec3>) {
-        let position_buffer: Vec<f32> = positions
-            .flat_map(|position| [position.x(), position.y(), position.z()])
-            .collect();
+    /// Draws the faces of translucent blocks, blending them with everything that has already been
+    /// drawn. This must come after everything else, and chunks are drawn from the furthest to the
+    /// nearest so that nearer faces blend over the ones behind them.
+    pub(crate) fn draw_translucent_cubes(&mut self) {
+        self.activate_cube_program()
+            .set_uniform_f32("opacity", &TRANSLUCENT_OPACITY);
+
+        let camera_position = self.camera_position;
+        let mut chunks: Vec<(f32, &ChunkBuffers)> = self
+            .translucent_chunk_buffers
+            .iter()
+            .filter(|(chunk_coordinates, buffers)| {
+                // Chunks with opaque faces were already counted when those faces were drawn.
+                let already_counted = self.chunk_buffers.contains_key(chunk_coordinates);
+                self.statistics.count_chunk(
+                    &self.camera_frustum,
+                    chunk_coordinates,
+                    buffers,
+                    already_counted,
+                )
+            })
+            .map(|(chunk_coordinates, buffers)| {
+                let Coordinates(x, y, z) = chunk_coordinates.origin();
+                let half_size = CHUNK_SIZE as f32 / 2.0;
+                let center = Vec3(
+                    x as f32 + half_size,
+                    y as f32 + half_size,
+                    z as f32 + half_size,
+                );
 
-        self.cube_count = position_buffer.len() / 3;
+                let offset = center - camera_position;
+                (offset.dot(&offset), buffers)
+            })
+            .collect();
+        chunks.sort_by(|(a, _), (b, _)| b.total_cmp(a));
 
         unsafe {
-            gl::BindBuffer(gl::ARRAY_BUFFER, self.position_array_buffer_id);
-            gl::BufferData(
-                gl::ARRAY_BUFFER,
-                (std::mem::size_of::<f32>() * 3 * self.cube_count) as isize,
-                position_buffer.as_ptr() as *const c_void,
-                gl::STATIC_DRAW,
-            );
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            // Translucent faces don't hide the faces behind them, so they don't write to the
+            // depth buffer.
+            gl::DepthMask(gl::FALSE);
+
+            for (_, buffers) in chunks {
+                gl::BindVertexArray(buffers.vertex_array_id);
+                gl::DrawArrays(gl::TRIANGLES, 0, buffers.vertex_count as GLint);
+            }
+
+            gl::DepthMask(gl::TRUE);
+            gl::Disable(gl::BLEND);
         }
+
+        self.activate_cube_program()
+            .set_uniform_f32("opacity", &1.0);
+    }
+
+    pub(crate) fn apply_chunk_update(&mut self, update: ChunkUpdate) {
+        match update {
+            ChunkUpdate::Changed(chunk_coordinates, mesh) => {
+                Self::replace_chunk_buffers(
+                    &mut self.chunk_buffers,
+                    chunk_coordinates,
+                    &mesh.vertices,
+                );
+                Self::replace_chunk_buffers(
+                    &mut self.translucent_chunk_buffers,
+                    chunk_coordinates,
+                    &mesh.translucent_vertices,
+                );
+            }
+            ChunkUpdate::Removed(chunk_coordinates) => {
+                for chunk_buffers in [&mut self.chunk_buffers, &mut self.translucent_chunk_buffers]
+                {
+                    if let Some(buffers) = chunk_buffers.remove(&chunk_coordinates) {
+                        buffers.delete();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Uploads the vertices to the chunk's buffers, or deletes the buffers if there aren't any
+    /// vertices to draw.
+    fn replace_chunk_buffers(
+        chunk_buffers: &mut HashMap<ChunkCoordinates, ChunkBuffers>,
+        chunk_coordinates: ChunkCoordinates,
+        vertices: &[Vertex],
+    ) {
+        if vertices.is_empty() {
+            if let Some(buffers) = chunk_buffers.remove(&chunk_coordinates) {
+                buffers.delete();
+            }
+        } else {
+            chunk_buffers
+                .entry(chunk_coordinates)
+                .or_insert_with(ChunkBuffers::new)
+                .upload(vertices);
+        }
+    }
+
+    /// Returns what was drawn since the screen was last cleared.
+    pub(crate) fn statistics(&self) -> DrawStatistics {
+        self.statistics
     }
 
     pub(crate) fn present(&mut self) {
@@ -331,34 +456,50 @@ impl Renderer {
 
     pub(crate) fn set_viewport(&mut self) {
         let window_size = self.window.inner_size();
-        let aspect_ratio = window_size.width as f32 / window_size.height as f32;
-
-        self.activate_cube_program()
-            .set_uniform_f32("aspect_ratio", &aspect_ratio);
-        self.activate_skybox_program()
-            .set_uniform_f32("aspect_ratio", &aspect_ratio);
+        self.aspect_ratio = window_size.width as f32 / window_size.height as f32;
 
         unsafe {
             gl::Viewport(0, 0, window_size.width as i32, window_size.height as i32);
         }
     }
 
+    /// Turns the darkening of the corners between blocks on or off, and returns whether it's now
+    /// on.
+    pub(crate) fn toggle_ambient_occlusion(&mut self) -> bool {
+        self.ambient_occlusion = !self.ambient_occlusion;
+
+        let strength = if self.ambient_occlusion { 1.0 } else { 0.0 };
+        self.activate_cube_program()
+            .set_uniform_f32("ambient_occlusion_strength", &strength);
+
+        self.ambient_occlusion
+    }
+
     pub(crate) fn set_camera(&mut self, camera: &Camera) {
-        let mut program = self.activate_cube_program();
-        program.set_uniform_vec3("camera_position", camera.position());
-        program.set_uniform_f32("camera_heading", &camera.heading());
-        program.set_uniform_f32("camera_pitch", &camera.pitch());
-
-        let mut program = self.activate_skybox_program();
-        program.set_uniform_f32("camera_heading", &camera.heading());
-        program.set_uniform_f32("camera_pitch", &camera.pitch());
+        self.camera_position = *camera.position();
+
+        let projection = Mat4::perspective(self.field_of_view, self.aspect_ratio, NEAR, FAR);
+        let target = *camera.position() + camera.direction();
+        let view_projection = projection * Mat4::look_at(camera.position(), &target, &camera.up());
+        self.camera_frustum = Frustum::from_view_projection(&view_projection);
+
+        self.activate_cube_program()
+            .set_uniform_mat4("view_projection", &view_projection);
+
+        // The sky is so far away that it turns with the camera but never gets any closer. The
+        // shader works out which part of it to draw at each point on the screen by undoing the
+        // camera's projection, so it needs the inverse.
+        let sky_view = Mat4::look_at(&Vec3(0.0, 0.0, 0.0), &camera.direction(), &camera.up());
+        if let Some(inverse) = (projection * sky_view).inverse() {
+            self.activate_skybox_program()
+                .set_uniform_mat4("inverse_view_projection", &inverse);
+        }
     }
 
     fn activate_cube_program(&mut self) -> ActiveProgram<'_> {
         unsafe {
             gl::UseProgram(self.cube_program.gl_id());
-            gl::BindVertexArray(self.cube_vertex_array_id);
-            gl::BindTexture(gl::TEXTURE_2D, self.cube_texture_id);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, self.cube_texture_id);
         }
 
         ActiveProgram {
@@ -379,16 +520,141 @@ impl Renderer {
     }
 }
 
+/// Counts of what was drawn in a frame.
+#[derive(Debug, Default, Copy, Clone)]
+pub(crate) struct DrawStatistics {
+    pub(crate) triangles: usize,
+    /// The chunks that were in view and drawn. A chunk with both opaque and translucent faces is
+    /// drawn in two passes, but only counted once.
+    pub(crate) drawn_chunks: usize,
+    /// The chunks that were skipped because they were out of view.
+    pub(crate) culled_chunks: usize,
+}
+
+impl DrawStatistics {
+    /// Counts the chunk as drawn if it's within the frustum, or culled if it isn't, unless it has
+    /// already been counted in an earlier pass. Returns whether it should be drawn.
+    fn count_chunk(
+        &mut self,
+        frustum: &Frustum,
+        chunk_coordinates: &ChunkCoordinates,
+        buffers: &ChunkBuffers,
+        already_counted: bool,
+    ) -> bool {
+        let Coordinates(x, y, z) = chunk_coordinates.origin();
+        let min = Vec3(x as f32, y as f32, z as f32);
+        let max = min + Vec3(CHUNK_SIZE as f32, CHUNK_SIZE as f32, CHUNK_SIZE as f32);
+
+        let visible = frustum.intersects_box(&min, &max);
+
+        if visible {
+            self.triangles += buffers.vertex_count / 3;
+        }
+
+        if !already_counted {
+            if visible {
+                self.drawn_chunks += 1;
+            } else {
+                self.culled_chunks += 1;
+            }
+        }
+
+        visible
+    }
+}
+
+/// The GPU buffers holding the mesh of a single chunk.
+struct ChunkBuffers {
+    vertex_array_id: GLuint,
+    vertex_buffer_id: GLuint,
+    vertex_count: usize,
+}
+
+impl ChunkBuffers {
+    fn new() -> Self {
+        const STRIDE: GLsizei = std::mem::size_of::<Vertex>() as GLsizei;
+
+        let vertex_array_id = unsafe {
+            let mut vertex_array_id = 0;
+            gl::GenVertexArrays(1, &mut vertex_array_id);
+            gl::BindVertexArray(vertex_array_id);
+            vertex_array_id
+        };
+
+        let vertex_buffer_id = unsafe {
+            let mut vertex_buffer_id = 0;
+            gl::GenBuffers(1, &mut vertex_buffer_id);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vertex_buffer_id);
+
+            let attributes = [
+                (3, std::mem::offset_of!(Vertex, position)),
+                (3, std::mem::offset_of!(Vertex, normal)),
+                (2, std::mem::offset_of!(Vertex, tex_coord)),
+                (1, std::mem::offset_of!(Vertex, texture_layer)),
+                (2, std::mem::offset_of!(Vertex, light)),
+                (1, std::mem::offset_of!(Vertex, ambient_occlusion)),
+            ];
+
+            for (location, (size, offset)) in attributes.into_iter().enumerate() {
+                gl::EnableVertexAttribArray(location as GLuint);
+                gl::VertexAttribPointer(
+                    location as GLuint,
+                    size,
+                    gl::FLOAT,
+                    gl::FALSE,
+                    STRIDE,
+                    offset as *const c_void,
+                );
+            }
+
+            vertex_buffer_id
+        };
+
+        Self {
+            vertex_array_id,
+            vertex_buffer_id,
+            vertex_count: 0,
+        }
+    }
+
+    fn upload(&mut self, vertices: &[Vertex]) {
+        self.vertex_count = vertices.len();
+
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vertex_buffer_id);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                std::mem::size_of_val(vertices) as isize,
+                vertices.as_ptr() as *const c_void,
+                gl::STATIC_DRAW,
+            );
+        }
+    }
+
+    fn delete(self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.vertex_buffer_id);
+            gl::DeleteVertexArrays(1, &self.vertex_array_id);
+        }
+    }
+}
+
 struct ActiveProgram<'a> {
     program: &'a mut Program,
 }
 
 impl<'a> ActiveProgram<'a> {
-    fn set_uniform_vec3(&mut self, name: &'static str, value: &Vec3) {
-        let Vec3(x, y, z) = *value;
+    fn set_uniform_mat4(&mut self, name: &'static str, value: &Mat4) {
+        // OpenGL expects the values a column at a time.
+        let columns = value.transpose();
 
         unsafe {
-            gl::Uniform3f(self.program.uniform_location(name), x, y, z);
+            gl::UniformMatrix4fv(
+                self.program.uniform_location(name),
+                1,
+                gl::FALSE,
+                columns.0.as_ptr() as *const f32,
+            );
         }
     }
 
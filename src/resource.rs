@@ -0,0 +1,516 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    block::{BlockId, NUM_BLOCK_TEXTURES},
+    math::RandomNumberGenerator,
+};
+
+/// The file within a resource pack directory that says which files hold which block textures.
+const MANIFEST_FILE_NAME: &str = "textures.txt";
+
+/// The width and height of the generated block textures, when no textures are loaded to set the
+/// size.
+const DEFAULT_TEXTURE_SIZE: usize = 8;
+
+/// The largest block texture that can be loaded. Every texture is stored in each layer of the
+/// array, so large textures quickly use up a lot of video memory.
+const MAX_TEXTURE_SIZE: usize = 256;
+
+/// The number of values stored for each pixel: red, green and blue.
+const VALUES_PER_PIXEL: usize = 3;
+
+/// How each generated layer of the block texture array is colored.
+struct GeneratedTextureStyle {
+    /// The color multiplied into the random noise of the texture.
+    tint: [f32; 3],
+    /// A different tint used for the given number of rows along the top edge of a texture of
+    /// `DEFAULT_TEXTURE_SIZE`, such as the grass hanging over the side of a grass block.
+    top_band: Option<([f32; 3], usize)>,
+}
+
+impl GeneratedTextureStyle {
+    const fn tint(tint: [f32; 3]) -> Self {
+        Self {
+            tint,
+            top_band: None,
+        }
+    }
+}
+
+const GENERATED_TEXTURE_STYLES: [GeneratedTextureStyle; NUM_BLOCK_TEXTURES] = [
+    GeneratedTextureStyle::tint([1.0, 1.0, 1.0]),    // Stone
+    GeneratedTextureStyle::tint([0.75, 0.55, 0.35]), // Dirt
+    GeneratedTextureStyle::tint([0.45, 0.85, 0.35]), // Grass
+    GeneratedTextureStyle::tint([1.0, 0.95, 0.65]),  // Sand
+    GeneratedTextureStyle::tint([0.3, 0.45, 1.0]),   // Water
+    GeneratedTextureStyle::tint([0.65, 0.45, 0.25]), // Wood
+    GeneratedTextureStyle::tint([0.25, 0.6, 0.2]),   // Leaves
+    GeneratedTextureStyle::tint([1.25, 1.2, 0.8]),   // Lamp
+    GeneratedTextureStyle::tint([0.4, 0.4, 0.45]),   // Coal ore
+    GeneratedTextureStyle::tint([0.95, 0.75, 0.6]),  // Iron ore
+    GeneratedTextureStyle::tint([1.3, 1.1, 0.35]),   // Gold ore
+    GeneratedTextureStyle::tint([0.55, 1.2, 1.25]),  // Diamond ore
+    GeneratedTextureStyle::tint([0.7, 0.68, 0.66]),  // Gravel
+    // Grass side
+    GeneratedTextureStyle {
+        tint: [0.75, 0.55, 0.35],
+        top_band: Some(([0.45, 0.85, 0.35], 2)),
+    },
+    GeneratedTextureStyle::tint([0.85, 0.7, 0.45]), // Wood rings
+];
+
+/// The images for every layer of the block texture array, ready to be uploaded to the GPU.
+#[derive(Debug, PartialEq)]
+pub(crate) struct TextureArray {
+    /// The width and height of every layer, in pixels.
+    pub(crate) size: usize,
+    /// The RGB values of each layer in turn. Each layer starts from its bottom row, which is the
+    /// order that OpenGL expects.
+    pub(crate) pixels: Vec<u8>,
+}
+
+impl TextureArray {
+    /// Generates random noise for every layer, in the colors of the blocks that use it.
+    pub(crate) fn generate(size: usize) -> Self {
+        let mut rng = RandomNumberGenerator::with_seed(42);
+        let mut pixels = Vec::with_capacity(size * size * VALUES_PER_PIXEL * NUM_BLOCK_TEXTURES);
+
+        for style in GENERATED_TEXTURE_STYLES.iter() {
+            let band_rows = style
+                .top_band
+                .map_or(0, |(_, rows)| rows * size / DEFAULT_TEXTURE_SIZE);
+
+            for row in 0..size {
+                let [red, green, blue] = match style.top_band {
+                    Some((tint, _)) if row >= size - band_rows => tint,
+                    _ => style.tint,
+                };
+
+                for _ in 0..size {
+                    let value = rng.gen_range(50, 200) as f32;
+                    pixels.push((value * red) as u8);
+                    pixels.push((value * green) as u8);
+                    pixels.push((value * blue) as u8);
+                }
+            }
+        }
+
+        Self { size, pixels }
+    }
+
+    fn layer_mut(&mut self, layer: u32) -> &mut [u8] {
+        let layer_size = self.size * self.size * VALUES_PER_PIXEL;
+        let start = layer as usize * layer_size;
+        &mut self.pixels[start..start + layer_size]
+    }
+}
+
+/// The block textures loaded from a resource pack.
+#[derive(Debug)]
+pub(crate) struct LoadedTextures {
+    pub(crate) textures: TextureArray,
+    /// The files named in the manifest that don't exist. The generated textures are used in their
+    /// place.
+    pub(crate) missing_files: Vec<PathBuf>,
+}
+
+/// Loads the block textures from the resource pack in the given directory.
+///
+/// The directory holds a `textures.txt` manifest, with a line for each texture of the form
+/// `<block> = <file>` or `<block> <face> = <file>`, where the face is `top`, `side` or `bottom`.
+/// Files are PNG images, relative to the directory, and every one of them must be the same size.
+/// Lines starting with `#` are ignored.
+///
+/// Any texture that isn't named in the manifest, or whose file is missing, is generated instead.
+/// A directory without a manifest gives the generated textures for every block.
+pub(crate) fn load_block_textures(directory: &Path) -> Result<LoadedTextures, ResourceError> {
+    let manifest_path = directory.join(MANIFEST_FILE_NAME);
+    let manifest = match fs::read_to_string(&manifest_path) {
+        Ok(manifest) => manifest,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(error) => return Err(ResourceError::Io(manifest_path, error)),
+    };
+
+    let mut images = Vec::new();
+    let mut missing_files = Vec::new();
+    let mut size = None;
+
+    for entry in parse_manifest(&manifest)? {
+        let path = directory.join(&entry.file);
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                missing_files.push(path);
+                continue;
+            }
+            Err(error) => return Err(ResourceError::Io(path, error)),
+        };
+
+        let image =
+            decode_png(&bytes).map_err(|error| ResourceError::Decode(path.clone(), error))?;
+        let expected_size = *size.get_or_insert(image.width);
+
+        if image.width != image.height
+            || image.width != expected_size
+            || !image.width.is_power_of_two()
+            || image.width > MAX_TEXTURE_SIZE
+        {
+            return Err(ResourceError::Size {
+                path,
+                width: image.width,
+                height: image.height,
+                expected: expected_size,
+            });
+        }
+
+        images.push((entry.layers, image));
+    }
+
+    let mut textures = TextureArray::generate(size.unwrap_or(DEFAULT_TEXTURE_SIZE));
+    for (layers, image) in images {
+        for layer in layers {
+            textures.layer_mut(layer).copy_from_slice(&image.pixels);
+        }
+    }
+
+    Ok(LoadedTextures {
+        textures,
+        missing_files,
+    })
+}
+
+/// A line of the manifest, naming the file drawn on some of the layers of the texture array.
+#[derive(Debug, PartialEq)]
+struct ManifestEntry {
+    layers: Vec<u32>,
+    file: PathBuf,
+}
+
+/// Parses the manifest, working out which layers of the texture array each file is drawn on.
+fn parse_manifest(manifest: &str) -> Result<Vec<ManifestEntry>, ResourceError> {
+    let mut entries = Vec::new();
+    // The line that set the file of each layer, so that blocks sharing a texture can't be given
+    // different files for it.
+    let mut layer_files: HashMap<u32, (usize, &str)> = HashMap::new();
+
+    for (index, line) in manifest.lines().enumerate() {
+        let line_number = index + 1;
+        let error = |message: String| ResourceError::Manifest {
+            line: line_number,
+            message,
+        };
+
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (key, file) = line
+            .split_once('=')
+            .ok_or_else(|| error("expected <block> = <file>".to_string()))?;
+        let (key, file) = (key.trim(), file.trim());
+
+        if file.is_empty() {
+            return Err(error(format!("no file given for {}", key)));
+        }
+
+        // Block names can contain spaces, so the face is only split off if it's a face name.
+        let (name, face) = match key.rsplit_once(' ') {
+            Some((name, face)) if ["top", "side", "bottom"].contains(&face) => {
+                (name.trim_end(), Some(face))
+            }
+            _ => (key, None),
+        };
+
+        let block = BlockId::from_name(name)
+            .filter(|block| *block != BlockId::AIR)
+            .ok_or_else(|| error(format!("unknown block {}", name)))?;
+        let textures = &block.block_type().textures;
+
+        let mut layers = match face {
+            Some("top") => vec![textures.top],
+            Some("side") => vec![textures.side],
+            Some("bottom") => vec![textures.bottom],
+            _ => vec![textures.top, textures.side, textures.bottom],
+        };
+        layers.sort();
+        layers.dedup();
+
+        for layer in layers.iter() {
+            if let Some((other_line, other_file)) = layer_files.insert(*layer, (line_number, file))
+            {
+                if other_file != file {
+                    return Err(error(format!(
+                        "{} shares its texture with line {}, so it can't use a different file",
+                        key, other_line
+                    )));
+                }
+            }
+        }
+
+        entries.push(ManifestEntry {
+            layers,
+            file: PathBuf::from(file),
+        });
+    }
+
+    Ok(entries)
+}
+
+/// An image decoded from a file.
+#[derive(Debug)]
+struct Image {
+    width: usize,
+    height: usize,
+    /// The RGB values of the image, starting from the bottom row.
+    pixels: Vec<u8>,
+}
+
+/// Decodes a PNG image, converting it to 8-bit RGB. Any transparency is dropped.
+fn decode_png(bytes: &[u8]) -> Result<Image, png::DecodingError> {
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut buffer)?;
+
+    let width = frame.width as usize;
+    let height = frame.height as usize;
+    let to_rgb = |pixel: &[u8]| match frame.color_type {
+        png::ColorType::Grayscale | png::ColorType::GrayscaleAlpha => {
+            [pixel[0], pixel[0], pixel[0]]
+        }
+        _ => [pixel[0], pixel[1], pixel[2]],
+    };
+
+    // PNG images start from the top row, and textures from the bottom.
+    let pixels = buffer[..frame.buffer_size()]
+        .chunks_exact(frame.line_size)
+        .rev()
+        .flat_map(|row| {
+            row[..width * frame.color_type.samples()]
+                .chunks_exact(frame.color_type.samples())
+                .flat_map(to_rgb)
+        })
+        .collect();
+
+    Ok(Image {
+        width,
+        height,
+        pixels,
+    })
+}
+
+#[derive(Debug)]
+pub(crate) enum ResourceError {
+    Io(PathBuf, io::Error),
+    Manifest {
+        line: usize,
+        message: String,
+    },
+    Decode(PathBuf, png::DecodingError),
+    /// The texture isn't square, or isn't the same size as the textures before it.
+    Size {
+        path: PathBuf,
+        width: usize,
+        height: usize,
+        expected: usize,
+    },
+}
+
+impl Display for ResourceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(path, error) => write!(f, "{}: {}", path.display(), error),
+            Self::Manifest { line, message } => {
+                write!(f, "{} line {}: {}", MANIFEST_FILE_NAME, line, message)
+            }
+            Self::Decode(path, error) => write!(f, "{}: {}", path.display(), error),
+            Self::Size {
+                path,
+                width,
+                height,
+                expected,
+            } => write!(
+                f,
+                "{} is {}x{}, but textures must be {}x{} (a power of two up to {})",
+                path.display(),
+                width,
+                height,
+                expected,
+                expected,
+                MAX_TEXTURE_SIZE
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::TestDirectory;
+
+    /// Encodes an image of the given color type, where every pixel has the same color.
+    fn encode_png(width: u32, height: u32, color_type: png::ColorType, pixel: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, width, height);
+        encoder.set_color(color_type);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let data = pixel.repeat((width * height) as usize);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&data).unwrap();
+        writer.finish().unwrap();
+
+        bytes
+    }
+
+    #[test]
+    fn images_are_decoded_to_rgb_from_the_bottom_row_up() {
+        // A 1x2 image that's red at the top and blue at the bottom.
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, 1, 2);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer
+            .write_image_data(&[255, 0, 0, 255, 0, 0, 255, 128])
+            .unwrap();
+        writer.finish().unwrap();
+
+        let image = decode_png(&bytes).unwrap();
+        assert_eq!((image.width, image.height), (1, 2));
+        assert_eq!(image.pixels, vec![0, 0, 255, 255, 0, 0]);
+
+        let image = decode_png(&encode_png(2, 2, png::ColorType::Grayscale, &[77])).unwrap();
+        assert_eq!(image.pixels, [77; 12]);
+
+        assert!(decode_png(b"not a png").is_err());
+    }
+
+    #[test]
+    fn manifest_lines_name_the_layers_of_block_faces() {
+        let grass = &BlockId::GRASS.block_type().textures;
+        let wood = &BlockId::WOOD.block_type().textures;
+        let manifest = "\
+# Textures for the test
+stone = stone.png
+
+grass top = grass_top.png
+coal ore = ores/coal.png
+wood = wood.png
+";
+
+        assert_eq!(
+            parse_manifest(manifest).unwrap(),
+            vec![
+                ManifestEntry {
+                    layers: vec![BlockId::STONE.block_type().textures.side],
+                    file: PathBuf::from("stone.png"),
+                },
+                ManifestEntry {
+                    layers: vec![grass.top],
+                    file: PathBuf::from("grass_top.png"),
+                },
+                ManifestEntry {
+                    layers: vec![BlockId::COAL_ORE.block_type().textures.side],
+                    file: PathBuf::from("ores/coal.png"),
+                },
+                ManifestEntry {
+                    layers: vec![wood.side, wood.top],
+                    file: PathBuf::from("wood.png"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_manifest_lines_are_errors() {
+        for (manifest, line) in [
+            ("stone.png", 1),
+            ("\nstone =", 2),
+            ("marble = marble.png", 1),
+            ("air = air.png", 1),
+            // Grass is dirt underneath, so they must use the same file.
+            ("dirt = dirt.png\ngrass bottom = roots.png", 2),
+        ] {
+            match parse_manifest(manifest) {
+                Err(ResourceError::Manifest { line: actual, .. }) => {
+                    assert_eq!(actual, line, "{}", manifest)
+                }
+                result => panic!("{:?} for {}", result, manifest),
+            }
+        }
+    }
+
+    #[test]
+    fn missing_textures_are_generated() {
+        let directory = TestDirectory::new("missing_textures_are_generated");
+        fs::create_dir_all(&directory.0).unwrap();
+
+        // Without a manifest, every texture is generated.
+        let loaded = load_block_textures(&directory.0).unwrap();
+        assert_eq!(
+            loaded.textures,
+            TextureArray::generate(DEFAULT_TEXTURE_SIZE)
+        );
+        assert!(loaded.missing_files.is_empty());
+
+        fs::write(
+            directory.0.join(MANIFEST_FILE_NAME),
+            "stone = stone.png\nsand = sand.png\n",
+        )
+        .unwrap();
+        fs::write(
+            directory.0.join("stone.png"),
+            encode_png(16, 16, png::ColorType::Rgb, &[10, 20, 30]),
+        )
+        .unwrap();
+
+        // The size of the loaded texture decides the size of the generated ones.
+        let mut loaded = load_block_textures(&directory.0).unwrap();
+        assert_eq!(loaded.missing_files, vec![directory.0.join("sand.png")]);
+        assert_eq!(loaded.textures.size, 16);
+
+        let stone = BlockId::STONE.block_type().textures.side;
+        assert_eq!(loaded.textures.layer_mut(stone), [10, 20, 30].repeat(256));
+
+        let sand = BlockId::SAND.block_type().textures.side;
+        let mut generated = TextureArray::generate(16);
+        assert_eq!(loaded.textures.layer_mut(sand), generated.layer_mut(sand));
+    }
+
+    #[test]
+    fn textures_must_be_square_and_the_same_size() {
+        let directory = TestDirectory::new("textures_must_be_square_and_the_same_size");
+        fs::create_dir_all(&directory.0).unwrap();
+        fs::write(
+            directory.0.join(MANIFEST_FILE_NAME),
+            "stone = stone.png\nsand = sand.png\n",
+        )
+        .unwrap();
+
+        for (stone_size, sand_size) in
+            [((16, 16), (8, 8)), ((16, 8), (16, 8)), ((12, 12), (12, 12))]
+        {
+            for (file, (width, height)) in [("stone.png", stone_size), ("sand.png", sand_size)] {
+                fs::write(
+                    directory.0.join(file),
+                    encode_png(width, height, png::ColorType::Rgb, &[0, 0, 0]),
+                )
+                .unwrap();
+            }
+
+            assert!(matches!(
+                load_block_textures(&directory.0),
+                Err(ResourceError::Size { .. })
+            ));
+        }
+    }
+}
@@ -0,0 +1,540 @@
+use std::{
+    fmt::Display,
+    fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+
+use crate::{
+    math::Vec3,
+    terrain::{NoiseAlgorithm, TerrainOptions},
+    world::{Chunk, ChunkCoordinates},
+};
+
+/// The version of the save format written by this build. This must be increased whenever the
+/// layout of any saved file changes, so that older saves can be recognized and migrated.
+///
+/// Version 1 was written while the terrain options in the world file and the numbering of the
+/// block IDs in the chunks were still changing, so saves from that version are rejected.
+const FORMAT_VERSION: u32 = 2;
+
+const WORLD_FILE_MAGIC: &[u8; 4] = b"IRWD";
+const REGION_FILE_MAGIC: &[u8; 4] = b"IRRG";
+
+/// The number of chunks along each side of the cube of chunks stored in a single region file.
+const REGION_SIZE: i32 = 8;
+const CHUNKS_PER_REGION: usize = (REGION_SIZE * REGION_SIZE * REGION_SIZE) as usize;
+
+/// The magic number and version, followed by an offset and length for every chunk.
+const REGION_HEADER_SIZE: usize = 8 + CHUNKS_PER_REGION * 8;
+
+/// Reads and writes a world saved in a directory on disk.
+///
+/// The directory holds a `world.dat` file with the state needed to recreate the world, and a
+/// `regions` directory of region files. Each region file holds the chunks within a cube of
+/// `REGION_SIZE` chunks, starting with a table giving the offset and length of every chunk's
+/// compressed blocks within the file. Chunks that have never been saved have an offset of zero.
+///
+/// All numbers are stored as little-endian.
+pub(crate) struct WorldStorage {
+    directory: PathBuf,
+    /// Held while chunks are being saved. A region file is read, changed and written back as a
+    /// whole, so two saves into the same region at once would lose one of them.
+    save_lock: Mutex<()>,
+}
+
+/// Everything other than the chunks that's needed to carry on playing a saved world.
+#[derive(Debug, PartialEq)]
+pub(crate) struct WorldMetadata {
+    pub(crate) terrain: TerrainOptions,
+    pub(crate) size: Option<u32>,
+    pub(crate) camera_position: Vec3,
+    pub(crate) camera_heading: f32,
+    pub(crate) camera_pitch: f32,
+}
+
+impl WorldStorage {
+    /// Opens the world saved in the given directory, creating the directory if it doesn't exist.
+    pub(crate) fn open(directory: &Path) -> Result<Self, StorageError> {
+        fs::create_dir_all(directory.join("regions"))?;
+
+        Ok(Self {
+            directory: directory.to_path_buf(),
+            save_lock: Mutex::new(()),
+        })
+    }
+
+    /// Returns the saved metadata, or `None` if the world has never been saved.
+    pub(crate) fn load_metadata(&self) -> Result<Option<WorldMetadata>, StorageError> {
+        let bytes = match fs::read(self.world_file_path()) {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error.into()),
+        };
+
+        let mut reader = ByteReader::new(&bytes);
+        reader.read_header(WORLD_FILE_MAGIC)?;
+
+        Ok(Some(WorldMetadata {
+            terrain: TerrainOptions {
+                seed: reader.read_u32()?,
+                noise: match reader.read_u32()? {
+                    0 => NoiseAlgorithm::Perlin,
+                    1 => NoiseAlgorithm::Simplex,
+                    noise => {
+                        return Err(StorageError::Corrupt(format!(
+                            "unknown noise algorithm {}",
+                            noise
+                        )))
+                    }
+                },
+                max_height: reader.read_u32()?,
+                cell_size: reader.read_f32()?,
+                octaves: reader.read_u32()?,
+                lacunarity: reader.read_f32()?,
+                persistence: reader.read_f32()?,
+                cave_cell_size: reader.read_f32()?,
+                cave_threshold: reader.read_f32()?,
+                cave_bottom: reader.read_u32()?,
+                cave_top: reader.read_u32()?,
+                biome_cell_size: reader.read_f32()?,
+                sea_level: reader.read_u32()?,
+            },
+            // An unlimited world is saved with a size of zero.
+            size: Some(reader.read_u32()?).filter(|size| *size > 0),
+            camera_position: Vec3(reader.read_f32()?, reader.read_f32()?, reader.read_f32()?),
+            camera_heading: reader.read_f32()?,
+            camera_pitch: reader.read_f32()?,
+        }))
+    }
+
+    pub(crate) fn save_metadata(&self, metadata: &WorldMetadata) -> Result<(), StorageError> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(WORLD_FILE_MAGIC);
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&metadata.terrain.seed.to_le_bytes());
+
+        let noise: u32 = match metadata.terrain.noise {
+            NoiseAlgorithm::Perlin => 0,
+            NoiseAlgorithm::Simplex => 1,
+        };
+        bytes.extend_from_slice(&noise.to_le_bytes());
+
+        bytes.extend_from_slice(&metadata.terrain.max_height.to_le_bytes());
+        bytes.extend_from_slice(&metadata.terrain.cell_size.to_le_bytes());
+        bytes.extend_from_slice(&metadata.terrain.octaves.to_le_bytes());
+        bytes.extend_from_slice(&metadata.terrain.lacunarity.to_le_bytes());
+        bytes.extend_from_slice(&metadata.terrain.persistence.to_le_bytes());
+        bytes.extend_from_slice(&metadata.terrain.cave_cell_size.to_le_bytes());
+        bytes.extend_from_slice(&metadata.terrain.cave_threshold.to_le_bytes());
+        bytes.extend_from_slice(&metadata.terrain.cave_bottom.to_le_bytes());
+        bytes.extend_from_slice(&metadata.terrain.cave_top.to_le_bytes());
+        bytes.extend_from_slice(&metadata.terrain.biome_cell_size.to_le_bytes());
+        bytes.extend_from_slice(&metadata.terrain.sea_level.to_le_bytes());
+        bytes.extend_from_slice(&metadata.size.unwrap_or(0).to_le_bytes());
+
+        let position = metadata.camera_position;
+        for value in [
+            position.x(),
+            position.y(),
+            position.z(),
+            metadata.camera_heading,
+            metadata.camera_pitch,
+        ] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+
+        write_file(&self.world_file_path(), &bytes)
+    }
+
+    /// Returns the saved chunks out of the given chunks, leaving out any that have never been
+    /// saved. Each region file is only read once, however many of the chunks it holds.
+    pub(crate) fn load_chunks(
+        &self,
+        chunk_coordinates: &[ChunkCoordinates],
+    ) -> Result<Vec<(ChunkCoordinates, Chunk)>, StorageError> {
+        let mut regions: Vec<(ChunkCoordinates, Region)> = Vec::new();
+        let mut chunks = Vec::new();
+
+        for chunk_coordinates in chunk_coordinates {
+            let (region_coordinates, index) = region_of(chunk_coordinates);
+
+            let region_index = match regions
+                .iter()
+                .position(|(coordinates, _)| *coordinates == region_coordinates)
+            {
+                Some(region_index) => region_index,
+                None => {
+                    let region = Region::load(&self.region_file_path(&region_coordinates))?;
+                    regions.push((region_coordinates, region));
+                    regions.len() - 1
+                }
+            };
+
+            let Some(compressed_blocks) = &regions[region_index].1.chunks[index] else {
+                continue;
+            };
+
+            let mut blocks = Vec::new();
+            ZlibDecoder::new(compressed_blocks.as_slice()).read_to_end(&mut blocks)?;
+
+            let chunk = Chunk::from_bytes(&blocks).ok_or_else(|| {
+                StorageError::Corrupt(format!("invalid chunk {:?}", chunk_coordinates))
+            })?;
+            chunks.push((*chunk_coordinates, chunk));
+        }
+
+        Ok(chunks)
+    }
+
+    /// Saves the given chunks, replacing any earlier saves of the same chunks.
+    pub(crate) fn save_chunks(
+        &self,
+        chunks: &[(ChunkCoordinates, &Chunk)],
+    ) -> Result<(), StorageError> {
+        let _save_guard = self.save_lock.lock().unwrap();
+
+        let mut regions: Vec<(ChunkCoordinates, Region)> = Vec::new();
+
+        for (chunk_coordinates, chunk) in chunks {
+            let (region_coordinates, index) = region_of(chunk_coordinates);
+
+            let region_index = match regions
+                .iter()
+                .position(|(coordinates, _)| *coordinates == region_coordinates)
+            {
+                Some(region_index) => region_index,
+                None => {
+                    let region = Region::load(&self.region_file_path(&region_coordinates))?;
+                    regions.push((region_coordinates, region));
+                    regions.len() - 1
+                }
+            };
+
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(&chunk.to_bytes())?;
+            regions[region_index].1.chunks[index] = Some(encoder.finish()?);
+        }
+
+        for (region_coordinates, region) in regions {
+            region.save(&self.region_file_path(&region_coordinates))?;
+        }
+
+        Ok(())
+    }
+
+    fn world_file_path(&self) -> PathBuf {
+        self.directory.join("world.dat")
+    }
+
+    fn region_file_path(&self, region_coordinates: &ChunkCoordinates) -> PathBuf {
+        let ChunkCoordinates(x, y, z) = *region_coordinates;
+        self.directory
+            .join("regions")
+            .join(format!("r.{}.{}.{}.dat", x, y, z))
+    }
+}
+
+/// Returns the coordinates of the region holding the chunk, measured in regions, along with the
+/// index of the chunk within the region.
+fn region_of(chunk_coordinates: &ChunkCoordinates) -> (ChunkCoordinates, usize) {
+    let ChunkCoordinates(x, y, z) = *chunk_coordinates;
+
+    let region_coordinates = ChunkCoordinates(
+        x.div_euclid(REGION_SIZE),
+        y.div_euclid(REGION_SIZE),
+        z.div_euclid(REGION_SIZE),
+    );
+
+    let local_x = x.rem_euclid(REGION_SIZE);
+    let local_y = y.rem_euclid(REGION_SIZE);
+    let local_z = z.rem_euclid(REGION_SIZE);
+    let index = ((local_y * REGION_SIZE + local_z) * REGION_SIZE + local_x) as usize;
+
+    (region_coordinates, index)
+}
+
+/// The compressed blocks of every chunk within a region file.
+struct Region {
+    chunks: Vec<Option<Vec<u8>>>,
+}
+
+impl Region {
+    /// Reads the region file at the given path, or returns an empty region if there isn't one.
+    fn load(path: &Path) -> Result<Self, StorageError> {
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                return Ok(Self {
+                    chunks: vec![None; CHUNKS_PER_REGION],
+                })
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        let mut reader = ByteReader::new(&bytes);
+        reader.read_header(REGION_FILE_MAGIC)?;
+
+        let mut chunks = Vec::with_capacity(CHUNKS_PER_REGION);
+
+        for _ in 0..CHUNKS_PER_REGION {
+            let offset = reader.read_u32()? as usize;
+            let length = reader.read_u32()? as usize;
+
+            if offset == 0 {
+                chunks.push(None);
+            } else {
+                let compressed_blocks = bytes.get(offset..(offset + length)).ok_or_else(|| {
+                    StorageError::Corrupt(format!("chunk data out of bounds in {}", path.display()))
+                })?;
+                chunks.push(Some(compressed_blocks.to_vec()));
+            }
+        }
+
+        Ok(Self { chunks })
+    }
+
+    fn save(&self, path: &Path) -> Result<(), StorageError> {
+        let mut header = Vec::with_capacity(REGION_HEADER_SIZE);
+        header.extend_from_slice(REGION_FILE_MAGIC);
+        header.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+
+        let mut body = Vec::new();
+
+        for chunk in self.chunks.iter() {
+            let (offset, length) = match chunk {
+                Some(compressed_blocks) => {
+                    let offset = REGION_HEADER_SIZE + body.len();
+                    body.extend_from_slice(compressed_blocks);
+                    (offset as u32, compressed_blocks.len() as u32)
+                }
+                None => (0, 0),
+            };
+
+            header.extend_from_slice(&offset.to_le_bytes());
+            header.extend_from_slice(&length.to_le_bytes());
+        }
+
+        header.extend_from_slice(&body);
+        write_file(path, &header)
+    }
+}
+
+/// Replaces the contents of the file in one step, so that a file that is being read by another
+/// thread, or a save that is interrupted partway through, never leaves a half-written file.
+fn write_file(path: &Path, bytes: &[u8]) -> Result<(), StorageError> {
+    let temporary_path = path.with_extension("tmp");
+    fs::write(&temporary_path, bytes)?;
+    fs::rename(&temporary_path, path)?;
+    Ok(())
+}
+
+/// Reads little-endian numbers from the start of a byte slice.
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    /// Checks the magic number and format version at the start of a file.
+    fn read_header(&mut self, magic: &[u8; 4]) -> Result<(), StorageError> {
+        if self.read_bytes::<4>()? != *magic {
+            return Err(StorageError::Corrupt("unrecognized file type".to_string()));
+        }
+
+        match self.read_u32()? {
+            FORMAT_VERSION => Ok(()),
+            version => Err(StorageError::UnsupportedVersion(version)),
+        }
+    }
+
+    fn read_u32(&mut self) -> Result<u32, StorageError> {
+        Ok(u32::from_le_bytes(self.read_bytes()?))
+    }
+
+    fn read_f32(&mut self) -> Result<f32, StorageError> {
+        Ok(f32::from_le_bytes(self.read_bytes()?))
+    }
+
+    fn read_bytes<const N: usize>(&mut self) -> Result<[u8; N], StorageError> {
+        if self.bytes.len() < N {
+            return Err(StorageError::Corrupt("unexpected end of file".to_string()));
+        }
+
+        let (bytes, rest) = self.bytes.split_at(N);
+        self.bytes = rest;
+        Ok(bytes.try_into().unwrap())
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum StorageError {
+    Io(io::Error),
+    /// The save was written by a different version of the game.
+    UnsupportedVersion(u32),
+    Corrupt(String),
+}
+
+impl Display for StorageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "save file error: {}", error),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "save format version {} is not supported (expected {})",
+                version, FORMAT_VERSION
+            ),
+            Self::Corrupt(error) => write!(f, "save file is corrupt: {}", error),
+        }
+    }
+}
+
+impl From<io::Error> for StorageError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// A directory for a test to save files in, which is removed once the test finishes.
+#[cfg(test)]
+pub(crate) struct TestDirectory(pub(crate) PathBuf);
+
+#[cfg(test)]
+impl TestDirectory {
+    pub(crate) fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("iridium-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        Self(path)
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestDirectory {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{block::BlockId, world::Coordinates};
+
+    #[test]
+    fn chunks_are_saved_and_loaded() {
+        let directory = TestDirectory::new("chunks_are_saved_and_loaded");
+        let storage = WorldStorage::open(&directory.0).unwrap();
+
+        // Chunks on either side of region borders, including in the negative regions.
+        let examples = [
+            ChunkCoordinates(0, 0, 0),
+            ChunkCoordinates(REGION_SIZE - 1, 1, 0),
+            ChunkCoordinates(REGION_SIZE, 1, 0),
+            ChunkCoordinates(-1, 0, -REGION_SIZE - 3),
+        ];
+
+        for (i, chunk_coordinates) in examples.iter().enumerate() {
+            let mut chunk = Chunk::empty();
+            let Coordinates(x, y, z) = chunk_coordinates.origin();
+            chunk.set_block(&Coordinates(x + i as i32, y + 2, z), BlockId::LAMP);
+
+            // Saving chunks one at a time must keep the chunks saved earlier in the same region.
+            storage
+                .save_chunks(&[(*chunk_coordinates, &chunk)])
+                .unwrap();
+        }
+
+        let chunks = storage.load_chunks(&examples).unwrap();
+        assert_eq!(chunks.len(), examples.len());
+
+        for (i, (chunk_coordinates, chunk)) in chunks.iter().enumerate() {
+            assert_eq!(*chunk_coordinates, examples[i]);
+
+            for coordinates in chunk_coordinates.block_coordinates() {
+                let origin = chunk_coordinates.origin();
+                let expected_block =
+                    if coordinates == Coordinates(origin.0 + i as i32, origin.1 + 2, origin.2) {
+                        BlockId::LAMP
+                    } else {
+                        BlockId::AIR
+                    };
+
+                assert_eq!(chunk.get_block(&coordinates), expected_block);
+            }
+        }
+
+        assert!(storage
+            .load_chunks(&[ChunkCoordinates(1, 0, 0)])
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn metadata_is_saved_and_loaded() {
+        let directory = TestDirectory::new("metadata_is_saved_and_loaded");
+        let storage = WorldStorage::open(&directory.0).unwrap();
+
+        assert_eq!(storage.load_metadata().unwrap(), None);
+
+        let metadata = WorldMetadata {
+            terrain: TerrainOptions {
+                seed: 1234,
+                noise: NoiseAlgorithm::Simplex,
+                max_height: 100,
+                cell_size: 12.5,
+                octaves: 3,
+                lacunarity: 2.5,
+                persistence: 0.25,
+                cave_cell_size: 8.0,
+                cave_threshold: 0.2,
+                cave_bottom: 5,
+                cave_top: 50,
+                biome_cell_size: 100.0,
+                sea_level: 20,
+            },
+            size: Some(512),
+            camera_position: Vec3(1.5, -20.0, 300.25),
+            camera_heading: 2.5,
+            camera_pitch: -0.5,
+        };
+        storage.save_metadata(&metadata).unwrap();
+
+        assert_eq!(storage.load_metadata().unwrap(), Some(metadata));
+    }
+
+    #[test]
+    fn saves_from_other_versions_are_rejected() {
+        let directory = TestDirectory::new("saves_from_other_versions_are_rejected");
+        let storage = WorldStorage::open(&directory.0).unwrap();
+
+        let chunk_coordinates = ChunkCoordinates(0, 0, 0);
+        let (region_coordinates, _) = region_of(&chunk_coordinates);
+
+        // Both older saves, with a layout that's since changed, and newer ones are rejected.
+        for other_version in [1, FORMAT_VERSION + 1] {
+            let mut bytes = WORLD_FILE_MAGIC.to_vec();
+            bytes.extend_from_slice(&other_version.to_le_bytes());
+            fs::write(storage.world_file_path(), bytes).unwrap();
+
+            assert!(matches!(
+                storage.load_metadata(),
+                Err(StorageError::UnsupportedVersion(version)) if version == other_version
+            ));
+
+            let mut bytes = REGION_FILE_MAGIC.to_vec();
+            bytes.extend_from_slice(&other_version.to_le_bytes());
+            fs::write(storage.region_file_path(&region_coordinates), bytes).unwrap();
+
+            assert!(matches!(
+                storage.load_chunks(&[chunk_coordinates]),
+                Err(StorageError::UnsupportedVersion(version)) if version == other_version
+            ));
+        }
+    }
+}
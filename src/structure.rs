@@ -0,0 +1,98 @@
+use crate::block::BlockId;
+
+/// The furthest that any structure reaches from its origin along the X or Z axis. Columns look
+/// this far outside of themselves for structures that reach into them.
+pub(crate) const MAX_STRUCTURE_RADIUS: i32 = 2;
+
+/// A small group of blocks placed on top of the terrain, such as a tree or a boulder.
+#[derive(Debug)]
+pub(crate) struct Structure {
+    /// Each block in the structure, as an offset from the structure's origin along with the type
+    /// of block. The origin is the block just above the surface that the structure stands on.
+    ///
+    /// Blocks are only placed where there's air, so earlier blocks take priority over later
+    /// blocks at the same offset, and the structure never replaces the terrain around it.
+    pub(crate) blocks: &'static [([i32; 3], BlockId)],
+}
+
+pub(crate) static TREE: Structure = Structure {
+    blocks: &[
+        // Trunk
+        ([0, 0, 0], BlockId::WOOD),
+        ([0, 1, 0], BlockId::WOOD),
+        ([0, 2, 0], BlockId::WOOD),
+        ([0, 3, 0], BlockId::WOOD),
+        // Lower canopy
+        ([-1, 2, -1], BlockId::LEAVES),
+        ([0, 2, -1], BlockId::LEAVES),
+        ([1, 2, -1], BlockId::LEAVES),
+        ([-1, 2, 0], BlockId::LEAVES),
+        ([1, 2, 0], BlockId::LEAVES),
+        ([-1, 2, 1], BlockId::LEAVES),
+        ([0, 2, 1], BlockId::LEAVES),
+        ([1, 2, 1], BlockId::LEAVES),
+        ([-2, 2, 0], BlockId::LEAVES),
+        ([2, 2, 0], BlockId::LEAVES),
+        ([0, 2, -2], BlockId::LEAVES),
+        ([0, 2, 2], BlockId::LEAVES),
+        // Middle canopy
+        ([-1, 3, -1], BlockId::LEAVES),
+        ([0, 3, -1], BlockId::LEAVES),
+        ([1, 3, -1], BlockId::LEAVES),
+        ([-1, 3, 0], BlockId::LEAVES),
+        ([1, 3, 0], BlockId::LEAVES),
+        ([-1, 3, 1], BlockId::LEAVES),
+        ([0, 3, 1], BlockId::LEAVES),
+        ([1, 3, 1], BlockId::LEAVES),
+        // Top of the canopy
+        ([0, 4, 0], BlockId::LEAVES),
+        ([-1, 4, 0], BlockId::LEAVES),
+        ([1, 4, 0], BlockId::LEAVES),
+        ([0, 4, -1], BlockId::LEAVES),
+        ([0, 4, 1], BlockId::LEAVES),
+        ([0, 5, 0], BlockId::LEAVES),
+    ],
+};
+
+/// A clump of leaves around a stub of wood, which keeps the leaves from decaying.
+pub(crate) static BUSH: Structure = Structure {
+    blocks: &[
+        ([0, 0, 0], BlockId::WOOD),
+        ([0, 1, 0], BlockId::LEAVES),
+        ([1, 0, 0], BlockId::LEAVES),
+        ([-1, 0, 0], BlockId::LEAVES),
+        ([0, 0, 1], BlockId::LEAVES),
+        ([0, 0, -1], BlockId::LEAVES),
+    ],
+};
+
+pub(crate) static BOULDER: Structure = Structure {
+    blocks: &[
+        ([0, 0, 0], BlockId::STONE),
+        ([1, 0, 0], BlockId::STONE),
+        ([0, 0, 1], BlockId::STONE),
+        ([1, 0, 1], BlockId::STONE),
+        ([0, 1, 0], BlockId::STONE),
+        ([1, 1, 1], BlockId::STONE),
+    ],
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn structures_stay_within_the_max_radius() {
+        for (name, structure) in [("tree", &TREE), ("bush", &BUSH), ("boulder", &BOULDER)] {
+            assert!(!structure.blocks.is_empty());
+
+            for ([x, _, z], _) in structure.blocks.iter() {
+                assert!(
+                    x.abs() <= MAX_STRUCTURE_RADIUS && z.abs() <= MAX_STRUCTURE_RADIUS,
+                    "{} reaches further than the max radius",
+                    name
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,1021 @@
+use std::str::FromStr;
+
+use crate::{
+    biome::{self, Biome, Climate},
+    block::BlockId,
+    math::{self, NoiseSource, RandomNumberGenerator, SimplexNoise, Vec2, Vec3},
+    ore,
+    structure::{Structure, MAX_STRUCTURE_RADIUS},
+    world::{Chunk, ChunkCoordinates, ColumnCoordinates, Coordinates, CHUNK_SIZE},
+};
+
+const DIRT_DEPTH: i32 = 3;
+
+/// Mixed into the seed of the cave noise, so that caves don't follow the shape of the hills.
+const CAVE_SEED_SALT: u32 = 0x5bd1_e995;
+
+/// Mixed into the seeds of the climate noise and the structure rolls, so that biomes and
+/// structures don't follow the shape of the hills or each other.
+const TEMPERATURE_SEED_SALT: u32 = 0x27d4_eb2f;
+const HUMIDITY_SEED_SALT: u32 = 0x1656_67b1;
+const STRUCTURE_SEED_SALT: u32 = 0x68e3_1da4;
+
+/// The kind of noise that the heightmap is made from.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum NoiseAlgorithm {
+    Perlin,
+    Simplex,
+}
+
+impl FromStr for NoiseAlgorithm {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "perlin" => Ok(Self::Perlin),
+            "simplex" => Ok(Self::Simplex),
+            _ => Err(format!("unknown noise algorithm {}", name)),
+        }
+    }
+}
+
+/// The settings that decide the shape of the terrain. Generating terrain with the same settings
+/// always gives the same blocks.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TerrainOptions {
+    pub(crate) seed: u32,
+    pub(crate) noise: NoiseAlgorithm,
+    /// The y coordinate of the highest block that can be generated.
+    pub(crate) max_height: u32,
+    /// The width of each cell in the noise grid of the heightmap's first octave, in blocks.
+    /// Larger cells spread hills out further.
+    pub(crate) cell_size: f32,
+    /// The number of layers of noise added together to make the heightmap.
+    pub(crate) octaves: u32,
+    /// How much smaller the cells of each octave are than the cells of the octave before it.
+    pub(crate) lacunarity: f32,
+    /// How much each octave affects the height compared to the octave before it.
+    pub(crate) persistence: f32,
+    /// The width of each cell in the noise grid that caves are carved from, in blocks. Smaller
+    /// cells make for smaller, more frequent caves.
+    pub(crate) cave_cell_size: f32,
+    /// Blocks are carved out where the cave noise is below this value, from 0.0 (no caves) up to
+    /// 1.0 (everything is carved out).
+    pub(crate) cave_threshold: f32,
+    /// The y coordinates of the lowest and highest blocks that can be carved out.
+    pub(crate) cave_bottom: u32,
+    pub(crate) cave_top: u32,
+    /// The width of each cell in the noise grids that decide the climate, in blocks. Larger
+    /// cells make for larger biomes.
+    pub(crate) biome_cell_size: f32,
+    /// The y coordinate of the surface of the sea. Any air between the ground and this height is
+    /// filled with water.
+    pub(crate) sea_level: u32,
+}
+
+impl Default for TerrainOptions {
+    fn default() -> Self {
+        Self {
+            seed: 32131,
+            noise: NoiseAlgorithm::Perlin,
+            max_height: 32,
+            cell_size: 32.0,
+            octaves: 4,
+            lacunarity: 2.0,
+            persistence: 0.5,
+            cave_cell_size: 16.0,
+            cave_threshold: 0.37,
+            cave_bottom: 1,
+            cave_top: 24,
+            biome_cell_size: 256.0,
+            sea_level: 11,
+        }
+    }
+}
+
+/// Decides which blocks make up the terrain. The generator only depends on its options and the
+/// position being generated, so chunks can be generated in any order and will always line up with
+/// their neighbors.
+pub(crate) struct TerrainGenerator {
+    options: TerrainOptions,
+    heightmap: FractalHeightmap,
+    caves: VolumeNoise,
+    temperature: Heightmap,
+    humidity: Heightmap,
+    max_height: i32,
+}
+
+impl TerrainGenerator {
+    pub(crate) fn new(options: TerrainOptions) -> Self {
+        Self {
+            heightmap: FractalHeightmap::new(&options),
+            caves: VolumeNoise::new(options.seed ^ CAVE_SEED_SALT, options.cave_cell_size),
+            temperature: Heightmap::new(
+                options.seed ^ TEMPERATURE_SEED_SALT,
+                options.biome_cell_size,
+            ),
+            humidity: Heightmap::new(options.seed ^ HUMIDITY_SEED_SALT, options.biome_cell_size),
+            max_height: options.max_height as i32,
+            options,
+        }
+    }
+
+    pub(crate) fn options(&self) -> &TerrainOptions {
+        &self.options
+    }
+
+    /// The number of chunks stacked on top of each other in every column of the world.
+    pub(crate) fn chunks_per_column(&self) -> i32 {
+        self.max_height / CHUNK_SIZE + 1
+    }
+
+    /// Generates every chunk in the given column, starting from the bottom.
+    pub(crate) fn generate_column(
+        &self,
+        column: &ColumnCoordinates,
+    ) -> Vec<(ChunkCoordinates, Chunk)> {
+        let chunk_ys: Vec<i32> = (0..self.chunks_per_column()).collect();
+        self.generate_chunks(column, &chunk_ys)
+    }
+
+    /// Generates the chunks at the given heights within a column, measured in chunks. The ground
+    /// is built up to the height of the heightmap and the sea is filled in above it, then ores are
+    /// scattered through the stone and caves are carved out, and finally structures are placed on
+    /// the surface. Every step only depends on the heightmap and the noise, never on the other
+    /// chunks of the column, so each chunk comes out the same however many of them are generated.
+    pub(crate) fn generate_chunks(
+        &self,
+        column: &ColumnCoordinates,
+        chunk_ys: &[i32],
+    ) -> Vec<(ChunkCoordinates, Chunk)> {
+        let mut chunks: Vec<(ChunkCoordinates, Chunk)> = chunk_ys
+            .iter()
+            .map(|y| (column.chunk(*y), Chunk::empty()))
+            .collect();
+
+        let Coordinates(x0, _, z0) = column.chunk(0).origin();
+        let mut heights = [0; (CHUNK_SIZE * CHUNK_SIZE) as usize];
+
+        for x in x0..(x0 + CHUNK_SIZE) {
+            for z in z0..(z0 + CHUNK_SIZE) {
+                let biome = self.biome_at(x, z);
+                let height = self.height_at(x, z);
+                let is_beach = self.is_beach(height);
+                heights[height_index(x, z)] = height;
+
+                for (chunk_coordinates, chunk) in chunks.iter_mut() {
+                    let Coordinates(_, y0, _) = chunk_coordinates.origin();
+
+                    for y in y0..(y0 + CHUNK_SIZE) {
+                        let block = if y > height {
+                            if !self.is_sea(y, height) {
+                                break;
+                            }
+                            BlockId::WATER
+                        } else if y < height - DIRT_DEPTH {
+                            BlockId::STONE
+                        } else if is_beach {
+                            BlockId::SAND
+                        } else if y == height {
+                            biome.surface_block
+                        } else {
+                            biome.filler_block
+                        };
+
+                        chunk.set_block(&Coordinates(x, y, z), block);
+                    }
+                }
+            }
+        }
+
+        for (chunk_coordinates, chunk) in chunks.iter_mut() {
+            ore::place_ores(self.options.seed, chunk_coordinates, chunk);
+        }
+
+        self.carve_caves(&mut chunks, &heights);
+        self.place_structures(column, &mut chunks);
+        chunks
+    }
+
+    /// Places every structure that reaches into the given column. Each structure belongs to the
+    /// surface block that it stands on, and every column that it reaches into places its own part
+    /// of it, so structures that cross the border between columns line up without being placed
+    /// twice.
+    fn place_structures(
+        &self,
+        column: &ColumnCoordinates,
+        chunks: &mut [(ChunkCoordinates, Chunk)],
+    ) {
+        let Coordinates(x0, _, z0) = column.chunk(0).origin();
+        let top = self.chunks_per_column() * CHUNK_SIZE - 1;
+        let xs = x0..(x0 + CHUNK_SIZE);
+        let zs = z0..(z0 + CHUNK_SIZE);
+
+        // Structures are always visited in the same order, so where two of them overlap, every
+        // column agrees on which one was placed first.
+        for origin_x in (x0 - MAX_STRUCTURE_RADIUS)..(x0 + CHUNK_SIZE + MAX_STRUCTURE_RADIUS) {
+            for origin_z in (z0 - MAX_STRUCTURE_RADIUS)..(z0 + CHUNK_SIZE + MAX_STRUCTURE_RADIUS) {
+                let Some((origin, structure)) = self.structure_at(origin_x, origin_z) else {
+                    continue;
+                };
+
+                for (offset, block) in structure.blocks.iter() {
+                    let coordinates = origin.offset(*offset);
+                    let Coordinates(x, y, z) = coordinates;
+
+                    if !xs.contains(&x) || !zs.contains(&z) || y < 0 || y > top {
+                        continue;
+                    }
+
+                    let Some((_, chunk)) = chunks
+                        .iter_mut()
+                        .find(|(chunk_coordinates, _)| *chunk_coordinates == coordinates.chunk())
+                    else {
+                        continue;
+                    };
+
+                    if chunk.get_block(&coordinates) == BlockId::AIR {
+                        chunk.set_block(&coordinates, *block);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the structure that stands on the given column, if there is one, along with the
+    /// structure's origin.
+    fn structure_at(&self, x: i32, z: i32) -> Option<(Coordinates, &'static Structure)> {
+        let height = self.height_at(x, z);
+        let surface = Coordinates(x, height, z);
+
+        // Beaches and the sea floor are left bare, and structures can't stand on ground that's
+        // been carved away.
+        if self.is_beach(height) || self.is_cave(&surface) {
+            return None;
+        }
+
+        // A single roll picks at most one structure, with each of the biome's decorations taking
+        // up its own share of the range.
+        let roll = math::hash_position(self.options.seed ^ STRUCTURE_SEED_SALT, x, z);
+        let mut chance = 0.0;
+
+        self.biome_at(x, z)
+            .decorations
+            .iter()
+            .find(|decoration| {
+                chance += decoration.chance;
+                roll < chance
+            })
+            .map(|decoration| (surface.offset([0, 1, 0]), decoration.structure))
+    }
+
+    /// Replaces the blocks within the cave band with air wherever the cave noise is below the
+    /// threshold. Blocks right underneath the sea are left alone, so that the sea doesn't sit on
+    /// top of open caves. The heights of the column are given by `height_index`.
+    fn carve_caves(&self, chunks: &mut [(ChunkCoordinates, Chunk)], heights: &[i32]) {
+        for (chunk_coordinates, chunk) in chunks.iter_mut() {
+            for coordinates in chunk_coordinates.block_coordinates() {
+                let block = chunk.get_block(&coordinates);
+                if block == BlockId::AIR || block.fluid_level().is_some() {
+                    continue;
+                }
+
+                let Coordinates(x, y, z) = coordinates;
+                let under_sea = self.is_sea(y + 1, heights[height_index(x, z)]);
+
+                if !under_sea && self.is_cave(&coordinates) {
+                    chunk.set_block(&coordinates, BlockId::AIR);
+                }
+            }
+        }
+    }
+
+    /// Whether the block at the given coordinates is carved out by the caves.
+    fn is_cave(&self, coordinates: &Coordinates) -> bool {
+        let Coordinates(_, y, _) = *coordinates;
+        let bottom = self.options.cave_bottom as i32;
+        let top = (self.options.cave_top as i32).min(self.max_height);
+
+        (bottom..=top).contains(&y)
+            && self.caves.density_at(&coordinates.center()) < self.options.cave_threshold
+    }
+
+    /// Whether the block at the given y coordinate is filled with water, in a column with its
+    /// highest block at the given height.
+    fn is_sea(&self, y: i32, height: i32) -> bool {
+        y > height && y <= (self.options.sea_level as i32).min(self.max_height)
+    }
+
+    /// Whether a column with its highest block at the given height is covered in sand, which is
+    /// the case for the sea floor and the shore along it.
+    fn is_beach(&self, height: i32) -> bool {
+        height <= self.options.sea_level as i32
+    }
+
+    /// Returns the biome that the given column belongs to.
+    pub(crate) fn biome_at(&self, x: i32, z: i32) -> &'static Biome {
+        biome::biome_for(&self.climate_at(x, z))
+    }
+
+    /// Returns the climate of the given column.
+    pub(crate) fn climate_at(&self, x: i32, z: i32) -> Climate {
+        let xz_position = Coordinates(x, 0, z).center().xz();
+
+        Climate {
+            temperature: self.temperature.noise_at(&xz_position),
+            humidity: self.humidity.noise_at(&xz_position),
+        }
+    }
+
+    /// Returns the y coordinate of the highest block in the given column.
+    fn height_at(&self, x: i32, z: i32) -> i32 {
+        const MIN_HEIGHT: i32 = 1;
+
+        let xz_position = Coordinates(x, 0, z).center().xz();
+        let noise = self.heightmap.height_at(&xz_position) - 0.5;
+
+        // Every biome near a border works out its own height, and the heights are averaged by how
+        // close the climate is to each biome, so that the ground doesn't jump up or down at the
+        // border.
+        let mut total_height = 0.0;
+        let mut total_weight = 0.0;
+
+        for (biome, weight) in biome::biome_weights(&self.climate_at(x, z)) {
+            total_height += (biome.height + noise * biome.height_scale) * weight;
+            total_weight += weight;
+        }
+
+        let height = (total_height / total_weight).clamp(0.0, 1.0);
+        let height_range = self.max_height - MIN_HEIGHT;
+
+        (height * height_range as f32) as i32 + MIN_HEIGHT
+    }
+}
+
+/// Returns the index of the given position on the x-z plane within the heights of the column
+/// that holds it.
+fn height_index(x: i32, z: i32) -> usize {
+    (x.rem_euclid(CHUNK_SIZE) * CHUNK_SIZE + z.rem_euclid(CHUNK_SIZE)) as usize
+}
+
+/// Adds several octaves of noise together (fractal Brownian motion). Each octave has smaller
+/// cells than the one before it and less influence on the height, so the first octave decides the
+/// overall shape of the land and the later octaves add finer detail to it.
+struct FractalHeightmap {
+    octaves: Vec<Box<dyn NoiseSource>>,
+    persistence: f32,
+}
+
+impl FractalHeightmap {
+    fn new(options: &TerrainOptions) -> Self {
+        Self::with_octaves(options, |seed, cell_size| match options.noise {
+            NoiseAlgorithm::Perlin => Box::new(Heightmap::new(seed, cell_size)),
+            NoiseAlgorithm::Simplex => Box::new(SimplexNoise::new(seed, cell_size)),
+        })
+    }
+
+    /// Builds each octave with `new_octave`, which is given the seed and cell size to use.
+    fn with_octaves(
+        options: &TerrainOptions,
+        mut new_octave: impl FnMut(u32, f32) -> Box<dyn NoiseSource>,
+    ) -> Self {
+        // Every octave has its own gradients, since octaves sharing the same gradients would line
+        // up with each other wherever their grids meet.
+        let mut rng = RandomNumberGenerator::with_seed(options.seed);
+        let mut cell_size = options.cell_size;
+        let mut octaves = Vec::with_capacity(options.octaves as usize);
+
+        for _ in 0..options.octaves {
+            octaves.push(new_octave(rng.gen_u32(), cell_size));
+            cell_size /= options.lacunarity;
+        }
+
+        Self {
+            octaves,
+            persistence: options.persistence,
+        }
+    }
+
+    /// Returns the height at the given position, scaled to be within [0.0, 1.0].
+    fn height_at(&self, xz_position: &Vec2) -> f32 {
+        let mut total_noise = 0.0;
+        let mut total_amplitude = 0.0;
+        let mut amplitude = 1.0;
+
+        for octave in self.octaves.iter() {
+            total_noise += octave.noise_at(xz_position) * amplitude;
+            total_amplitude += amplitude;
+            amplitude *= self.persistence;
+        }
+
+        // Dividing by the total amplitude keeps the noise within [-1.0, 1.0] however many octaves
+        // there are.
+        (total_noise / total_amplitude + 1.0) * 0.5
+    }
+}
+
+/// Perlin noise in three dimensions, used for features such as caves that can't be described by
+/// a height.
+struct VolumeNoise {
+    seed: u32,
+    cell_size: f32,
+}
+
+impl VolumeNoise {
+    fn new(seed: u32, cell_size: f32) -> Self {
+        Self { seed, cell_size }
+    }
+
+    /// Returns the noise at the given position, scaled to be within [0.0, 1.0].
+    fn density_at(&self, position: &Vec3) -> f32 {
+        (self.noise_at(position) + 1.0) * 0.5
+    }
+
+    /// Returns the raw noise value at the given position, which is within [-1.0, 1.0].
+    fn noise_at(&self, position: &Vec3) -> f32 {
+        let normalized_position = *position * (1.0 / self.cell_size);
+        let Vec3(x, y, z) = normalized_position;
+
+        let xi = x.floor() as i64;
+        let yi = y.floor() as i64;
+        let zi = z.floor() as i64;
+
+        let x_frac = x - x.floor();
+        let y_frac = y - y.floor();
+        let z_frac = z - z.floor();
+
+        // Blend the corners along X, then along Z, then along Y.
+        let mut y_values = [0.0; 2];
+
+        for (dy, y_value) in y_values.iter_mut().enumerate() {
+            let mut z_values = [0.0; 2];
+
+            for (dz, z_value) in z_values.iter_mut().enumerate() {
+                let corner_y = yi + dy as i64;
+                let corner_z = zi + dz as i64;
+
+                let x0 = self.value_from_corner(&normalized_position, xi, corner_y, corner_z);
+                let x1 = self.value_from_corner(&normalized_position, xi + 1, corner_y, corner_z);
+                *z_value = math::interpolate(x0, x1, x_frac);
+            }
+
+            *y_value = math::interpolate(z_values[0], z_values[1], z_frac);
+        }
+
+        math::interpolate(y_values[0], y_values[1], y_frac)
+    }
+
+    /// Returns the influence that the gradient at the given grid corner has on the position.
+    fn value_from_corner(&self, normalized_position: &Vec3, xi: i64, yi: i64, zi: i64) -> f32 {
+        let corner_position = Vec3(xi as f32, yi as f32, zi as f32);
+
+        let gradient = math::hash_gradient_3d(self.seed, xi, yi, zi);
+
+        gradient.dot(&(*normalized_position - corner_position))
+    }
+}
+
+/// Describes how elevation varies across the x-z plane.
+///
+/// The heightmap is a grid of Perlin noise cells with a gradient at every corner, so it can be
+/// sampled at any position.
+#[derive(Debug)]
+struct Heightmap {
+    cell_size: f32,
+    gradients: GradientSource,
+}
+
+/// Where a heightmap gets the gradient at each corner of its grid from.
+#[derive(Debug)]
+enum GradientSource {
+    /// Each gradient is picked from a hash of the seed and the corner, so the grid never repeats.
+    Hashed(u32),
+    /// A list of gradients that repeats every `num_x_cells` cells along the X axis and every
+    /// `num_z_cells` cells along the Z axis.
+    #[cfg(test)]
+    Repeating {
+        gradients: Vec<Vec2>,
+        num_x_cells: u32,
+        num_z_cells: u32,
+    },
+}
+
+impl Heightmap {
+    /// Constructs a Perlin noise grid with random gradients.
+    fn new(seed: u32, cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            gradients: GradientSource::Hashed(seed),
+        }
+    }
+
+    /// Constructs a Perlin noise grid with the given gradients.
+    ///
+    /// There must be one gradient for each corner in the repeating grid. The first gradient is
+    /// used for x_min / z_min in the grid. The next gradient moves along the x-axis first until it
+    /// reaches x_max, then it moves onto the next row in the z axis. Corners past the end of the
+    /// grid wrap around to the start. This function asserts that the number of gradients matches
+    /// the size of the grid.
+    #[cfg(test)]
+    fn with_gradients(
+        gradients: Vec<Vec2>,
+        cell_size: f32,
+        num_x_cells: u32,
+        num_z_cells: u32,
+    ) -> Self {
+        let expected_num_gradients = num_x_cells * num_z_cells;
+
+        assert_eq!(
+            gradients.len(),
+            expected_num_gradients as usize,
+            "wrong number of gradients for heightmap"
+        );
+
+        Self {
+            cell_size,
+            gradients: GradientSource::Repeating {
+                gradients,
+                num_x_cells,
+                num_z_cells,
+            },
+        }
+    }
+
+    /// Returns the height at the given position, scaled to be within [0.0, 1.0].
+    #[cfg(test)]
+    fn height_at(&self, xz_position: &Vec2) -> f32 {
+        (self.noise_at(xz_position) + 1.0) * 0.5
+    }
+
+    fn normalize_position(&self, xz_position: &Vec2) -> Vec2 {
+        *xz_position / self.cell_size
+    }
+
+    /// Returns the influence that the gradient at the given grid corner has on the position.
+    fn height_from_corner(&self, normalized_position: &Vec2, xi: i64, zi: i64) -> f32 {
+        let corner_position = Vec2(xi as f32, zi as f32);
+        let gradient = self.gradient_at_index(xi, zi);
+
+        let offset = *normalized_position - corner_position;
+        gradient.dot(&offset)
+    }
+
+    fn gradient_at_index(&self, xi: i64, zi: i64) -> Vec2 {
+        match &self.gradients {
+            GradientSource::Hashed(seed) => math::hash_gradient_2d(*seed, xi, zi),
+            #[cfg(test)]
+            GradientSource::Repeating {
+                gradients,
+                num_x_cells,
+                num_z_cells,
+            } => {
+                let xi = xi.rem_euclid(*num_x_cells as i64) as usize;
+                let zi = zi.rem_euclid(*num_z_cells as i64) as usize;
+                gradients[zi * *num_x_cells as usize + xi]
+            }
+        }
+    }
+}
+
+impl NoiseSource for Heightmap {
+    /// Returns the raw Perlin noise value at the given position, which is within [-1.0, 1.0].
+    fn noise_at(&self, xz_position: &Vec2) -> f32 {
+        let normalized_position = self.normalize_position(xz_position);
+        let Vec2(x, z) = normalized_position;
+
+        let xi = x.floor() as i64;
+        let zi = z.floor() as i64;
+
+        let x0z0_height = self.height_from_corner(&normalized_position, xi, zi);
+        let x0z1_height = self.height_from_corner(&normalized_position, xi, zi + 1);
+        let x1z0_height = self.height_from_corner(&normalized_position, xi + 1, zi);
+        let x1z1_height = self.height_from_corner(&normalized_position, xi + 1, zi + 1);
+
+        let x_frac = x - x.floor();
+        let z_frac = z - z.floor();
+
+        let x0_height = math::interpolate(x0z0_height, x0z1_height, z_frac);
+        let x1_height = math::interpolate(x1z0_height, x1z1_height, z_frac);
+
+        math::interpolate(x0_height, x1_height, x_frac)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{HashMap, HashSet},
+        f32::consts::*,
+    };
+
+    use super::*;
+
+    #[test]
+    fn perlin_noise_single_grid_cell() {
+        // Create a 2D Perlin noise grid with four gradients, which repeats after every two cells.
+        // In this example, all gradients are unit vectors that point along either the X or Z axis.
+        let gradients = vec![
+            Vec2::from_angle(0.0),
+            Vec2::from_angle(-PI),
+            Vec2::from_angle(FRAC_PI_2),
+            Vec2::from_angle(-FRAC_PI_2),
+        ];
+
+        let heightmap = Heightmap::with_gradients(gradients, 1.0, 2, 2);
+
+        let examples = [
+            // Always zero at the grid corner
+            (Vec2(0.0, 0.0), 0.0),
+            // Halfway between two corners on X axis, both with gradients facing inward (max strength)
+            (Vec2(0.5, 0.0), 0.5),
+            // Strength drops as approaching either corner. Should drop equally on both sides.
+            (Vec2(0.25, 0.0), 0.375),
+            (Vec2(0.75, 0.0), 0.375),
+            // Along the Z axis, gradient faces away so should be negative
+            (Vec2(0.0, 0.5), -0.25),
+            (Vec2(0.0, 0.25), -0.1875),
+            (Vec2(0.0, 0.75), -0.1875),
+            // In the middle. The two gradients along Z=0 contribute 0.5, and the two gradients at
+            // Z=1 are facing opposite directions and cancel each other out (so 0.0), which results
+            // in an interpolated value of 0.25.
+            (Vec2(0.5, 0.5), 0.25),
+        ];
+
+        for (position, expected) in examples.into_iter() {
+            let actual = heightmap.noise_at(&position);
+
+            assert!((actual - expected).abs() < 0.00001);
+        }
+    }
+
+    #[test]
+    fn perlin_noise_varying_cell_size() {
+        let small_cell_heightmap = Heightmap::new(32131, 1.0);
+        let big_cell_heightmap = Heightmap::new(32131, 16.0);
+
+        // Positions for the small cell and big cell heightmaps. The left and right values should
+        // return the same height value since they're in the same spot relative to the size of the
+        // grid.
+        let examples = [
+            (Vec2(0.0, 0.0), Vec2(0.0, 0.0)),
+            (Vec2(1.5, 0.5), Vec2(24.0, 8.0)),
+            (Vec2(1.0, 1.0), Vec2(16.0, 16.0)),
+            (Vec2(-3.25, 0.5), Vec2(-52.0, 8.0)),
+        ];
+
+        for (small_position, big_position) in examples.into_iter() {
+            let small_cell_height = small_cell_heightmap.height_at(&small_position);
+            let big_cell_height = big_cell_heightmap.height_at(&big_position);
+
+            assert_eq!(small_cell_height, big_cell_height);
+        }
+    }
+
+    #[test]
+    fn perlin_noise_outside_of_cell_range() {
+        let heightmap = Heightmap::new(32131, 16.0);
+
+        // Far from the origin, the noise carries on within range without repeating itself, even
+        // at distances that are a power of two number of cells apart.
+        for distance in [256.0, 4096.0, 65536.0] {
+            let differences = (0..100)
+                .filter(|i| {
+                    let (x, z) = (*i as f32 * 3.7, *i as f32 * -1.3);
+                    let height = heightmap.height_at(&Vec2(x, z));
+                    let far_height = heightmap.height_at(&Vec2(x + distance, z + distance));
+
+                    assert!((0.0..=1.0).contains(&far_height));
+                    (height - far_height).abs() > 0.0001
+                })
+                .count();
+
+            assert!(differences > 90, "{} apart", distance);
+        }
+    }
+
+    #[test]
+    fn perlin_noise_is_the_same_for_the_same_seed() {
+        // Recorded from a known seed, so that changes to the noise (which would change the terrain
+        // of every world) are noticed.
+        let heightmap = Heightmap::new(32131, 16.0);
+        let examples = [
+            (Vec2(4.8, 11.2), 0.024424),
+            (Vec2(-196.0, 72.0), -0.092590),
+            (Vec2(1601.6, -606.4), -0.035020),
+        ];
+
+        for (position, expected) in examples.into_iter() {
+            assert!((heightmap.noise_at(&position) - expected).abs() < 0.00001);
+        }
+    }
+
+    #[test]
+    fn fractal_noise_stays_within_range() {
+        let examples = [
+            TerrainOptions::default(),
+            TerrainOptions {
+                octaves: 1,
+                ..Default::default()
+            },
+            TerrainOptions {
+                seed: 7,
+                cell_size: 5.0,
+                octaves: 8,
+                lacunarity: 3.0,
+                persistence: 1.0,
+                ..Default::default()
+            },
+            TerrainOptions {
+                octaves: 6,
+                lacunarity: 1.5,
+                persistence: 0.9,
+                ..Default::default()
+            },
+            TerrainOptions {
+                noise: NoiseAlgorithm::Simplex,
+                ..Default::default()
+            },
+        ];
+
+        for options in examples.iter() {
+            let heightmap = FractalHeightmap::new(options);
+            assert_eq!(heightmap.octaves.len(), options.octaves as usize);
+
+            for x in -100..100 {
+                for z in -100..100 {
+                    let position = Vec2(x as f32 * 1.37, z as f32 * 0.91);
+                    let height = heightmap.height_at(&position);
+
+                    assert!(
+                        (0.0..=1.0).contains(&height),
+                        "height {} at {:?} is out of range with {:?}",
+                        height,
+                        position,
+                        options
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fractal_noise_octaves_have_independent_gradients() {
+        // Keep a copy of every octave that's built, so the octaves can be compared.
+        let mut octaves = Vec::new();
+        FractalHeightmap::with_octaves(&TerrainOptions::default(), |seed, cell_size| {
+            octaves.push(Heightmap::new(seed, cell_size));
+            Box::new(Heightmap::new(seed, cell_size))
+        });
+
+        for (i, octave) in octaves.iter().enumerate() {
+            for other_octave in octaves[(i + 1)..].iter() {
+                let num_shared_gradients = (0..64)
+                    .flat_map(|xi| (0..64).map(move |zi| (xi, zi)))
+                    .filter(|(xi, zi)| {
+                        let a = octave.gradient_at_index(*xi, *zi);
+                        let b = other_octave.gradient_at_index(*xi, *zi);
+                        a.0 == b.0 && a.1 == b.1
+                    })
+                    .count();
+
+                assert!(num_shared_gradients < 64 * 64 / 100);
+            }
+        }
+
+        let cell_sizes: Vec<f32> = octaves.iter().map(|octave| octave.cell_size).collect();
+        assert_eq!(cell_sizes, [32.0, 16.0, 8.0, 4.0]);
+    }
+
+    #[test]
+    fn volume_noise_stays_within_range_without_repeating() {
+        let noise = VolumeNoise::new(32131, 4.0);
+        let mut differences = 0;
+
+        for x in -20..20 {
+            for y in -20..20 {
+                for z in -20..20 {
+                    let position = Vec3(x as f32 * 0.73, y as f32 * 1.11, z as f32 * 0.57);
+                    let value = noise.noise_at(&position);
+                    assert!((-1.0..=1.0).contains(&value));
+
+                    let far_position = position + Vec3(256.0, -256.0, 256.0);
+                    if (value - noise.noise_at(&far_position)).abs() > 0.001 {
+                        differences += 1;
+                    }
+                }
+            }
+        }
+
+        assert!(differences > 40 * 40 * 40 * 9 / 10);
+
+        // Always zero at grid corners.
+        assert_eq!(noise.noise_at(&Vec3(8.0, -4.0, 12.0)), 0.0);
+    }
+
+    #[test]
+    fn caves_are_only_carved_within_their_band() {
+        let options = TerrainOptions {
+            cave_threshold: 0.45,
+            cave_bottom: 4,
+            cave_top: 10,
+            ..Default::default()
+        };
+        let without_caves = TerrainGenerator::new(TerrainOptions {
+            cave_threshold: 0.0,
+            ..options.clone()
+        });
+        let with_caves = TerrainGenerator::new(options);
+
+        let mut num_carved_blocks = 0;
+
+        for column in [ColumnCoordinates(0, 0), ColumnCoordinates(-5, 3)] {
+            let solid_chunks = without_caves.generate_column(&column);
+            let carved_chunks = with_caves.generate_column(&column);
+
+            for ((chunk_coordinates, solid_chunk), (_, carved_chunk)) in
+                solid_chunks.iter().zip(carved_chunks.iter())
+            {
+                for coordinates in chunk_coordinates.block_coordinates() {
+                    let solid_block = solid_chunk.get_block(&coordinates);
+                    let carved_block = carved_chunk.get_block(&coordinates);
+
+                    if solid_block != carved_block {
+                        assert_eq!(carved_block, BlockId::AIR);
+                        assert!((4..=10).contains(&coordinates.1));
+                        num_carved_blocks += 1;
+                    }
+                }
+            }
+        }
+
+        assert!(num_carved_blocks > 0);
+    }
+
+    #[test]
+    fn biomes_decide_the_surface_blocks() {
+        let generator = TerrainGenerator::new(TerrainOptions {
+            cave_threshold: 0.0,
+            ..Default::default()
+        });
+
+        let mut biome_names = HashSet::new();
+
+        for column_x in -8..8 {
+            for column_z in -8..8 {
+                // Spread the columns out so that they cover several biomes.
+                let column = ColumnCoordinates(column_x * 12, column_z * 12);
+                let chunks = generator.generate_column(&column);
+                let get_block = |coordinates: &Coordinates| {
+                    let (_, chunk) = &chunks[(coordinates.1 / CHUNK_SIZE) as usize];
+                    chunk.get_block(coordinates)
+                };
+
+                let Coordinates(x0, _, z0) = column.chunk(0).origin();
+                for (x, z) in [(x0, z0), (x0 + 7, z0 + 3), (x0 + 15, z0 + 12)] {
+                    let biome = generator.biome_at(x, z);
+                    let height = generator.height_at(x, z);
+                    biome_names.insert(biome.name);
+
+                    if generator.is_beach(height) {
+                        continue;
+                    }
+
+                    // Ore veins can break through to the surface of biomes made of stone.
+                    let is_expected = |coordinates: Coordinates, expected: BlockId| {
+                        let block = get_block(&coordinates);
+                        block == expected || ore::ORES.iter().any(|ore| ore.block == block)
+                    };
+
+                    assert!(is_expected(Coordinates(x, height, z), biome.surface_block));
+                    assert!(is_expected(
+                        Coordinates(x, height - 1, z),
+                        biome.filler_block
+                    ));
+                }
+            }
+        }
+
+        assert!(biome_names.len() > 1);
+    }
+
+    #[test]
+    fn structures_are_whole_across_column_borders() {
+        let generator = TerrainGenerator::new(TerrainOptions::default());
+
+        let mut chunks = HashMap::new();
+        for column_x in -3..3 {
+            for column_z in -3..3 {
+                chunks.extend(generator.generate_column(&ColumnCoordinates(column_x, column_z)));
+            }
+        }
+
+        let get_block =
+            |coordinates: &Coordinates| chunks[&coordinates.chunk()].get_block(coordinates);
+        let mut num_crossing_structures = 0;
+
+        // Only structures that are entirely within the generated columns are checked.
+        let min = -3 * CHUNK_SIZE + MAX_STRUCTURE_RADIUS;
+        let max = 3 * CHUNK_SIZE - MAX_STRUCTURE_RADIUS;
+
+        for x in min..max {
+            for z in min..max {
+                let Some((origin, structure)) = generator.structure_at(x, z) else {
+                    continue;
+                };
+
+                // Every part of the structure was placed by whichever column it fell in. The
+                // blocks might belong to an overlapping structure instead, but never to air.
+                let mut columns = HashSet::new();
+                for (offset, _) in structure.blocks.iter() {
+                    let coordinates = origin.offset(*offset);
+                    assert_ne!(get_block(&coordinates), BlockId::AIR);
+                    let ChunkCoordinates(column_x, _, column_z) = coordinates.chunk();
+                    columns.insert((column_x, column_z));
+                }
+
+                if columns.len() > 1 {
+                    num_crossing_structures += 1;
+                }
+            }
+        }
+
+        assert!(num_crossing_structures > 0);
+    }
+
+    #[test]
+    fn heights_blend_across_biome_borders() {
+        let generator = TerrainGenerator::new(TerrainOptions::default());
+        let mut num_borders = 0;
+        let mut steepest_border = 0;
+        let mut steepest_hill = 0;
+
+        for x in -2000..2000 {
+            let step = (generator.height_at(x, 40) - generator.height_at(x + 1, 40)).abs();
+
+            if generator.biome_at(x, 40).name != generator.biome_at(x + 1, 40).name {
+                num_borders += 1;
+                steepest_border = steepest_border.max(step);
+            } else {
+                steepest_hill = steepest_hill.max(step);
+            }
+        }
+
+        // Crossing into another biome is no steeper than walking over the hills within a biome.
+        assert!(num_borders > 0);
+        assert!(steepest_border <= steepest_hill);
+    }
+
+    #[test]
+    fn chunks_generated_on_their_own_match_the_whole_column() {
+        let generator = TerrainGenerator::new(TerrainOptions::default());
+
+        // Only the chunks that haven't been saved are generated when a column is loaded, so each
+        // chunk must come out the same as it would as part of the whole column, including the
+        // caves, the sea and the structures that reach across chunks.
+        for column in [ColumnCoordinates(0, 0), ColumnCoordinates(-3, 7)] {
+            let whole_column = generator.generate_column(&column);
+
+            for (chunk_coordinates, chunk) in whole_column.iter() {
+                let ChunkCoordinates(_, y, _) = *chunk_coordinates;
+                let single_chunks = generator.generate_chunks(&column, &[y]);
+                assert_eq!(single_chunks.len(), 1);
+
+                let (single_coordinates, single_chunk) = &single_chunks[0];
+                assert_eq!(single_coordinates, chunk_coordinates);
+
+                for coordinates in chunk_coordinates.block_coordinates() {
+                    assert_eq!(
+                        single_chunk.get_block(&coordinates),
+                        chunk.get_block(&coordinates)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn generating_a_column_is_deterministic() {
+        let generator = TerrainGenerator::new(TerrainOptions::default());
+
+        // Generating a column must not depend on anything but the seed, so generating it again
+        // gives back the same blocks.
+        let column = ColumnCoordinates(-3, 7);
+        let first = generator.generate_column(&column);
+        let second = generator.generate_column(&column);
+
+        assert_eq!(first.len(), generator.chunks_per_column() as usize);
+
+        for ((first_coordinates, first_chunk), (second_coordinates, second_chunk)) in
+            first.iter().zip(second.iter())
+        {
+            assert_eq!(first_coordinates, second_coordinates);
+
+            for coordinates in first_coordinates.block_coordinates() {
+                assert_eq!(
+                    first_chunk.get_block(&coordinates),
+                    second_chunk.get_block(&coordinates)
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::BTreeSet;
+
+use crate::{
+    math::{self, RandomNumberGenerator},
+    world::{ChunkCoordinates, Coordinates, World, CHUNK_SIZE},
+};
+
+/// Mixed into the seed of the random ticks, so that they don't line up with anything generated
+/// from the seed.
+const TICK_SEED_SALT: u32 = 0x5bd1_e995;
+
+/// The number of blocks picked at random in every loaded chunk on each tick. A block is picked
+/// once every `CHUNK_SIZE³ / RANDOM_TICKS_PER_CHUNK` ticks on average.
+const RANDOM_TICKS_PER_CHUNK: u32 = 2;
+
+/// The behavior of a type of block when it's ticked, given the world, the coordinates of the
+/// block, and a random number generator to make its decisions with.
+pub(crate) type TickCallback = fn(&mut World, &Coordinates, &mut RandomNumberGenerator);
+
+/// Keeps track of the ticks that blocks have asked for, so they can be run in order once they
+/// come due.
+#[derive(Debug, Default)]
+pub(crate) struct TickScheduler {
+    /// The tick that each block is due on, ordered by tick and then by coordinates, so that ticks
+    /// due at the same time always run in the same order.
+    scheduled: BTreeSet<(u64, Coordinates)>,
+}
+
+impl TickScheduler {
+    /// Schedules a tick for the block at the given coordinates. A block can be scheduled more
+    /// than once, but only gets one tick on each tick number.
+    pub(crate) fn schedule(&mut self, coordinates: &Coordinates, tick: u64) {
+        self.scheduled.insert((tick, *coordinates));
+    }
+
+    /// Removes and returns every block whose tick is due by the given tick number, in the order
+    /// that they should run in.
+    pub(crate) fn take_due(&mut self, tick: u64) -> Vec<Coordinates> {
+        let not_due = self
+            .scheduled
+            .split_off(&(tick + 1, Coordinates(i32::MIN, i32::MIN, i32::MIN)));
+        let due = std::mem::replace(&mut self.scheduled, not_due);
+
+        due.into_iter()
+            .map(|(_, coordinates)| coordinates)
+            .collect()
+    }
+}
+
+/// Returns a random number generator for a block that's being ticked. Each one depends only on
+/// the seed, the tick number and the block's coordinates, so replaying the same ticks gives the
+/// same results no matter which chunks are loaded.
+pub(crate) fn block_rng(seed: u32, tick: u64, coordinates: &Coordinates) -> RandomNumberGenerator {
+    let Coordinates(x, y, z) = *coordinates;
+
+    // Ticks wrap around after 2³² updates, which is more than two years at 60 updates a second.
+    let tick_seed = (seed ^ TICK_SEED_SALT).wrapping_add(tick as u32);
+    RandomNumberGenerator::with_seed(math::hash_coordinates(tick_seed, x, y, z))
+}
+
+/// Picks the blocks within the chunk that get a random tick on the given tick number.
+pub(crate) fn random_tick_coordinates(
+    seed: u32,
+    tick: u64,
+    chunk_coordinates: &ChunkCoordinates,
+) -> Vec<Coordinates> {
+    let origin = chunk_coordinates.origin();
+    let mut rng = block_rng(seed, tick, &origin);
+
+    (0..RANDOM_TICKS_PER_CHUNK)
+        .map(|_| {
+            origin.offset([
+                rng.gen_range(0, CHUNK_SIZE as u32) as i32,
+                rng.gen_range(0, CHUNK_SIZE as u32) as i32,
+                rng.gen_range(0, CHUNK_SIZE as u32) as i32,
+            ])
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scheduled_ticks_are_taken_in_order_once_due() {
+        let mut scheduler = TickScheduler::default();
+        scheduler.schedule(&Coordinates(5, 0, 0), 12);
+        scheduler.schedule(&Coordinates(-3, 7, 2), 10);
+        scheduler.schedule(&Coordinates(-4, 7, 2), 10);
+        scheduler.schedule(&Coordinates(-4, 7, 2), 10);
+        scheduler.schedule(&Coordinates(1, 1, 1), 11);
+
+        assert_eq!(scheduler.take_due(9), vec![]);
+        assert_eq!(
+            scheduler.take_due(11),
+            vec![
+                Coordinates(-4, 7, 2),
+                Coordinates(-3, 7, 2),
+                Coordinates(1, 1, 1)
+            ]
+        );
+        assert_eq!(scheduler.take_due(20), vec![Coordinates(5, 0, 0)]);
+        assert_eq!(scheduler.take_due(20), vec![]);
+    }
+
+    #[test]
+    fn random_ticks_are_repeatable_and_within_the_chunk() {
+        let chunk_coordinates = ChunkCoordinates(-2, 1, 4);
+
+        for tick in 0..100 {
+            let picked = random_tick_coordinates(32131, tick, &chunk_coordinates);
+            assert_eq!(picked.len(), RANDOM_TICKS_PER_CHUNK as usize);
+            assert_eq!(
+                picked,
+                random_tick_coordinates(32131, tick, &chunk_coordinates)
+            );
+
+            for coordinates in picked {
+                assert_eq!(coordinates.chunk(), chunk_coordinates);
+            }
+        }
+
+        assert_ne!(
+            random_tick_coordinates(32131, 0, &chunk_coordinates),
+            random_tick_coordinates(32131, 1, &chunk_coordinates)
+        );
+    }
+}
@@ -1,5 +1,7 @@
 use std::time::{Duration, Instant};
 
+use crate::render::DrawStatistics;
+
 const FPS_INTERVAL: Duration = Duration::from_secs(3);
 
 pub(crate) struct FrameCounter {
@@ -15,14 +17,20 @@ impl FrameCounter {
         }
     }
 
-    pub(crate) fn finish_frame(&mut self, current_instant: Instant) {
+    pub(crate) fn finish_frame(&mut self, current_instant: Instant, statistics: DrawStatistics) {
         self.counter += 1;
 
         let time_since_last_printout = current_instant - self.last_instant;
 
         if time_since_last_printout > FPS_INTERVAL {
             let frames_per_second = self.counter as f32 / time_since_last_printout.as_secs_f32();
-            println!("FPS: {}", frames_per_second);
+            println!(
+                "FPS: {}, triangles: {}, chunks drawn: {}, culled: {}",
+                frames_per_second,
+                statistics.triangles,
+                statistics.drawn_chunks,
+                statistics.culled_chunks
+            );
 
             self.counter = 0;
             self.last_instant = current_instant;
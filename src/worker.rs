@@ -0,0 +1,189 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+};
+
+use crate::{
+    mesh::{ChunkMesh, MeshingMode},
+    storage::{StorageError, WorldStorage},
+    terrain::TerrainGenerator,
+    world::{Chunk, ChunkCoordinates, ChunkNeighborhood, ColumnCoordinates},
+};
+
+/// Work that can be done away from the main thread.
+pub(crate) enum Job {
+    /// Loads the chunks in the column from storage, generating any that haven't been saved.
+    GenerateColumn(ColumnCoordinates),
+    /// Saves the modified chunks of a column that has been unloaded.
+    SaveColumn(ColumnCoordinates, Vec<(ChunkCoordinates, Arc<Chunk>)>),
+    /// Builds the mesh of the chunk at the center of the neighborhood. The revision
+    /// is handed back with the result so that stale results can be recognized.
+    MeshChunk(u64, ChunkNeighborhood, MeshingMode),
+}
+
+/// The outcome of a finished `Job`.
+pub(crate) enum JobResult {
+    ColumnGenerated(ColumnCoordinates, Vec<(ChunkCoordinates, Chunk)>),
+    ColumnSaved(ColumnCoordinates, Result<(), StorageError>),
+    ChunkMeshed(ChunkCoordinates, u64, ChunkMesh),
+}
+
+/// A set of background threads that generate, save and mesh chunks. Jobs are handed out to whichever
+/// thread is free first, and results are collected on the main thread with `finished_jobs`.
+pub(crate) struct WorkerPool {
+    job_sender: Option<Sender<Job>>,
+    result_receiver: Receiver<JobResult>,
+    threads: Vec<JoinHandle<()>>,
+    shutting_down: Arc<AtomicBool>,
+}
+
+impl WorkerPool {
+    pub(crate) fn new(
+        generator: Arc<TerrainGenerator>,
+        storage: Option<Arc<WorldStorage>>,
+    ) -> Self {
+        // Leave one core free for the main thread, which is busy rendering.
+        let num_threads = thread::available_parallelism()
+            .map(|parallelism| parallelism.get().saturating_sub(1))
+            .unwrap_or(1)
+            .max(1);
+
+        let (job_sender, job_receiver) = mpsc::channel::<Job>();
+        let (result_sender, result_receiver) = mpsc::channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        let shutting_down = Arc::new(AtomicBool::new(false));
+
+        let threads = (0..num_threads)
+            .map(|i| {
+                let job_receiver = Arc::clone(&job_receiver);
+                let result_sender = result_sender.clone();
+                let generator = Arc::clone(&generator);
+                let storage = storage.clone();
+                let shutting_down = Arc::clone(&shutting_down);
+
+                thread::Builder::new()
+                    .name(format!("chunk-worker-{}", i))
+                    .spawn(move || loop {
+                        // The lock is only held while waiting for the next job, not while running
+                        // it, so the other threads can pick up jobs in the meantime.
+                        let job = match job_receiver.lock().unwrap().recv() {
+                            Ok(job) => job,
+                            Err(_) => break,
+                        };
+
+                        if shutting_down.load(Ordering::Relaxed) {
+                            break;
+                        }
+
+                        let result = match job {
+                            Job::GenerateColumn(column) => JobResult::ColumnGenerated(
+                                column,
+                                load_column(&column, &generator, storage.as_deref()),
+                            ),
+                            Job::SaveColumn(column, chunks) => JobResult::ColumnSaved(
+                                column,
+                                save_column(&chunks, storage.as_deref()),
+                            ),
+                            Job::MeshChunk(revision, neighborhood, mode) => JobResult::ChunkMeshed(
+                                neighborhood.center(),
+                                revision,
+                                ChunkMesh::build(&neighborhood, mode),
+                            ),
+                        };
+
+                        if result_sender.send(result).is_err() {
+                            break;
+                        }
+                    })
+                    .unwrap()
+            })
+            .collect();
+
+        Self {
+            job_sender: Some(job_sender),
+            result_receiver,
+            threads,
+            shutting_down,
+        }
+    }
+
+    pub(crate) fn submit(&self, job: Job) {
+        if let Some(job_sender) = &self.job_sender {
+            job_sender.send(job).unwrap();
+        }
+    }
+
+    /// Returns the results of every job that has finished since the last call, without waiting
+    /// for any jobs that are still running.
+    pub(crate) fn finished_jobs(&self) -> impl Iterator<Item = JobResult> + '_ {
+        self.result_receiver.try_iter()
+    }
+}
+
+/// Returns the saved chunks in the column, filling in any chunks that haven't been saved with
+/// newly generated ones.
+fn load_column(
+    column: &ColumnCoordinates,
+    generator: &TerrainGenerator,
+    storage: Option<&WorldStorage>,
+) -> Vec<(ChunkCoordinates, Chunk)> {
+    let chunk_coordinates: Vec<ChunkCoordinates> = (0..generator.chunks_per_column())
+        .map(|y| column.chunk(y))
+        .collect();
+
+    let mut chunks = match storage.map(|storage| storage.load_chunks(&chunk_coordinates)) {
+        Some(Ok(saved_chunks)) => saved_chunks,
+        Some(Err(error)) => {
+            eprintln!("failed to load column {:?}: {}", column, error);
+            Vec::new()
+        }
+        None => Vec::new(),
+    };
+
+    let missing_ys: Vec<i32> = chunk_coordinates
+        .iter()
+        .filter(|coordinates| chunks.iter().all(|(saved, _)| saved != *coordinates))
+        .map(|ChunkCoordinates(_, y, _)| *y)
+        .collect();
+
+    if !missing_ys.is_empty() {
+        chunks.extend(generator.generate_chunks(column, &missing_ys));
+        chunks.sort_by_key(|(ChunkCoordinates(_, y, _), _)| *y);
+    }
+
+    chunks
+}
+
+/// Saves the given chunks. Does nothing if the world isn't being saved.
+fn save_column(
+    chunks: &[(ChunkCoordinates, Arc<Chunk>)],
+    storage: Option<&WorldStorage>,
+) -> Result<(), StorageError> {
+    let Some(storage) = storage else {
+        return Ok(());
+    };
+
+    let chunks: Vec<(ChunkCoordinates, &Chunk)> = chunks
+        .iter()
+        .map(|(chunk_coordinates, chunk)| (*chunk_coordinates, chunk.as_ref()))
+        .collect();
+
+    storage.save_chunks(&chunks)
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        // Any jobs still waiting in the queue are abandoned, and closing the job channel wakes up
+        // the idle threads so that every thread exits once it finishes its current job.
+        self.shutting_down.store(true, Ordering::Relaxed);
+        self.job_sender = None;
+
+        for thread in self.threads.drain(..) {
+            thread.join().unwrap();
+        }
+    }
+}
@@ -1,138 +1,1001 @@
-use crate::math::{self, RandomNumberGenerator, Vec2, Vec3};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::Arc,
+};
+
+use crate::{
+    biome::{Biome, Climate},
+    block::BlockId,
+    fluid::{self, FLUID_TICK_INTERVAL},
+    light::{self, LightChannel, LightVolume, MAX_LIGHT},
+    math::Vec3,
+    mesh::{ChunkMesh, MeshingMode},
+    physics::Aabb,
+    storage::{StorageError, WorldMetadata, WorldStorage},
+    terrain::{TerrainGenerator, TerrainOptions},
+    tick::{self, TickScheduler},
+    worker::{Job, JobResult, WorkerPool},
+};
 
 const MOUSE_SENSITIVITY: f32 = 0.01;
-const MOVE_SPEED: f32 = 0.5;
+
+// Speeds and accelerations are measured in blocks per update.
+const FLY_SPEED: f32 = 0.5;
+const WALK_SPEED: f32 = 0.07;
+const JUMP_SPEED: f32 = 0.15;
+const GRAVITY: f32 = 0.008;
+const TERMINAL_VELOCITY: f32 = 1.2;
+
+/// The size of the box that the player collides with. The camera is at eye height, centered
+/// horizontally within the box.
+const PLAYER_WIDTH: f32 = 0.6;
+const PLAYER_HEIGHT: f32 = 1.8;
+const EYE_HEIGHT: f32 = 1.6;
+
+const DEFAULT_VIEW_DISTANCE: u32 = 8;
+
+/// How far away from the camera blocks can be removed or placed, in blocks.
+const MAX_REACH: f32 = 8.0;
+
+/// The most columns that can be waiting to be generated at once. New columns are requested
+/// closest-first, so keeping the queue short means that the columns nearest to the camera are
+/// generated first even while it's moving.
+const MAX_PENDING_COLUMNS: usize = 16;
+
+/// The number of blocks along each side of a cubic chunk.
+pub(crate) const CHUNK_SIZE: i32 = 16;
+const BLOCKS_PER_CHUNK: usize = (CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE) as usize;
 
 pub(crate) struct World {
-    x_width: u32,
-    z_depth: u32,
     camera: Camera,
-    heights: Vec<i32>,
+    chunks: HashMap<ChunkCoordinates, Arc<Chunk>>,
+    generator: Arc<TerrainGenerator>,
+    workers: WorkerPool,
+    storage: Option<Arc<WorldStorage>>,
+    /// Chunks that have been edited since they were generated or last saved. Chunks that haven't
+    /// been edited don't need to be saved, since they can be generated again.
+    modified_chunks: HashSet<ChunkCoordinates>,
+    /// Modified chunks that have been unloaded but not saved yet, either because they're still
+    /// being saved or because saving them failed. These are newer than the saved chunks, so
+    /// they're used in place of them if their column is loaded again.
+    unsaved_chunks: HashMap<ChunkCoordinates, Arc<Chunk>>,
+    /// Columns that are being saved. These aren't loaded again until the save has finished, so
+    /// that loading them doesn't read the chunks from before the save.
+    saving_columns: HashSet<ColumnCoordinates>,
+    view_distance: i32,
+    /// The number of blocks along the X and Z axes that the world is limited to, if it's limited.
+    size: Option<i32>,
+    loaded_columns: HashSet<ColumnCoordinates>,
+    pending_columns: HashSet<ColumnCoordinates>,
+    changed_chunks: HashSet<ChunkCoordinates>,
+    removed_chunks: Vec<ChunkCoordinates>,
+    /// The revision of the most recent mesh job started for each chunk. Only results for the
+    /// latest revision are kept, since older jobs were working with outdated blocks.
+    mesh_revisions: HashMap<ChunkCoordinates, u64>,
+    next_mesh_revision: u64,
+    finished_meshes: HashMap<ChunkCoordinates, ChunkMesh>,
+    meshing_mode: MeshingMode,
+    /// The block placed by `place_block_at_target`.
+    selected_block: BlockId,
+    movement_mode: MovementMode,
+    /// The number of times the world has been updated since it was opened.
+    ticks: u64,
+    /// Blocks that have changed, or had a neighbor change, since the last update. These are the
+    /// only blocks that can need to do something, such as fall now that nothing is holding them
+    /// up.
+    block_updates: HashSet<Coordinates>,
+    tick_scheduler: TickScheduler,
+    /// Blocks that have changed, or had a neighbor change, since the last step of the fluid
+    /// simulation. Fluid can only start or stop flowing where something has changed.
+    fluid_updates: HashSet<Coordinates>,
+}
+
+/// The settings used to create a world.
+#[derive(Debug, Clone)]
+pub(crate) struct WorldOptions {
+    pub(crate) terrain: TerrainOptions,
+    /// Limits the world to a square of this many blocks along the X and Z axes, starting at the
+    /// origin. This must be a multiple of `CHUNK_SIZE`. The world is unlimited if this is `None`.
+    pub(crate) size: Option<u32>,
+    /// The distance in chunks that columns are loaded within around the camera.
+    pub(crate) view_distance: u32,
+    pub(crate) meshing_mode: MeshingMode,
+}
+
+impl Default for WorldOptions {
+    fn default() -> Self {
+        Self {
+            terrain: TerrainOptions::default(),
+            size: None,
+            view_distance: DEFAULT_VIEW_DISTANCE,
+            meshing_mode: MeshingMode::Greedy,
+        }
+    }
+}
+
+/// How the camera moves through the world.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum MovementMode {
+    /// The camera walks along the ground, and is pulled down by gravity and stopped by solid
+    /// blocks.
+    Walking,
+    /// The camera flies freely and passes straight through blocks.
+    Flying,
+}
+
+/// A change to the blocks in a chunk that the renderer needs to know about.
+pub(crate) enum ChunkUpdate {
+    /// The chunk was loaded or modified, and this is its new mesh.
+    Changed(ChunkCoordinates, ChunkMesh),
+    /// The chunk was unloaded and should no longer be drawn.
+    Removed(ChunkCoordinates),
 }
 
 impl World {
-    pub(crate) fn new(x_width: u32, y_height: u32, z_depth: u32) -> Self {
-        let starting_position = Vec3((x_width / 2) as f32, (y_height + 1) as f32, 0.0);
+    /// Creates a world that isn't saved. Columns of chunks are loaded as the camera comes within
+    /// the view distance of them, and unloaded once it moves away.
+    pub(crate) fn new(options: WorldOptions) -> Self {
+        Self::with_storage(options, None)
+    }
+
+    /// Opens the world saved in `storage`, or starts a new world there if nothing has been saved
+    /// yet. Saved chunks are used in place of generating them, and a saved world keeps the
+    /// terrain options and size that it was created with.
+    pub(crate) fn load(
+        storage: WorldStorage,
+        mut options: WorldOptions,
+    ) -> Result<Self, StorageError> {
+        let metadata = storage.load_metadata()?;
+
+        if let Some(metadata) = &metadata {
+            options.terrain = metadata.terrain.clone();
+            options.size = metadata.size;
+        }
+
+        let mut world = Self::with_storage(options, Some(Arc::new(storage)));
+
+        if let Some(metadata) = metadata {
+            world.camera.position = metadata.camera_position;
+            world.camera.heading = metadata.camera_heading;
+            world.camera.pitch = metadata.camera_pitch;
+        }
+
+        Ok(world)
+    }
+
+    fn with_storage(options: WorldOptions, storage: Option<Arc<WorldStorage>>) -> Self {
+        // Start in the middle of the world, with the player's feet above the highest block that
+        // could be generated.
+        let center = options.size.map_or(0.0, |size| size as f32 / 2.0);
+        let height = (options.terrain.max_height + 2) as f32 + EYE_HEIGHT;
+        let starting_position = Vec3(center, height, center);
 
         let camera = Camera {
             position: starting_position,
-            velocity: Vec3(0.0, 0.0, 0.0),
+            movement: Vec3(0.0, 0.0, 0.0),
+            vertical_velocity: 0.0,
+            on_ground: false,
             heading: 0.0,
             pitch: 0.0,
         };
 
-        let xz_area = (x_width * z_depth) as usize;
+        let generator = Arc::new(TerrainGenerator::new(options.terrain));
+        let workers = WorkerPool::new(Arc::clone(&generator), storage.clone());
+
+        Self {
+            camera,
+            chunks: HashMap::new(),
+            generator,
+            workers,
+            storage,
+            modified_chunks: HashSet::new(),
+            unsaved_chunks: HashMap::new(),
+            saving_columns: HashSet::new(),
+            view_distance: options.view_distance as i32,
+            size: options.size.map(|size| size as i32),
+            loaded_columns: HashSet::new(),
+            pending_columns: HashSet::new(),
+            changed_chunks: HashSet::new(),
+            removed_chunks: Vec::new(),
+            mesh_revisions: HashMap::new(),
+            next_mesh_revision: 0,
+            finished_meshes: HashMap::new(),
+            meshing_mode: options.meshing_mode,
+            selected_block: BlockId::STONE,
+            movement_mode: MovementMode::Walking,
+            ticks: 0,
+            block_updates: HashSet::new(),
+            tick_scheduler: TickScheduler::default(),
+            fluid_updates: HashSet::new(),
+        }
+    }
+
+    pub(crate) fn update(&mut self) {
+        match self.movement_mode {
+            MovementMode::Walking => self.walk(),
+            MovementMode::Flying => self.fly(),
+        }
+
+        self.update_blocks();
+
+        self.ticks += 1;
+        self.tick_blocks();
+
+        if self.ticks.is_multiple_of(FLUID_TICK_INTERVAL) {
+            self.update_fluids();
+        }
+
+        self.receive_finished_jobs();
+        self.stream_columns();
+        self.mesh_changed_chunks();
+    }
+
+    fn fly(&mut self) {
+        let velocity = (self.camera.movement * FLY_SPEED).rotate_y(self.camera.heading);
+        self.camera.position = self.camera.position + velocity;
+    }
+
+    fn walk(&mut self) {
+        // Hold the camera in place until the terrain around it has loaded, rather than letting it
+        // fall through the world.
+        let camera_column = ColumnCoordinates::containing(&self.camera.position);
+        if self.is_column_in_world(&camera_column) && !self.loaded_columns.contains(&camera_column)
+        {
+            return;
+        }
+
+        if self.camera.on_ground && self.camera.movement.y() > 0.0 {
+            self.camera.vertical_velocity = JUMP_SPEED;
+        }
+
+        self.camera.vertical_velocity =
+            (self.camera.vertical_velocity - GRAVITY).max(-TERMINAL_VELOCITY);
+
+        // Moving diagonally shouldn't be any faster than moving straight ahead.
+        let horizontal_movement = self.camera.movement.set_y(0.0);
+        let horizontal_velocity = if horizontal_movement.length() > 0.0 {
+            horizontal_movement.normalize() * WALK_SPEED
+        } else {
+            horizontal_movement
+        };
+
+        let velocity = horizontal_velocity
+            .rotate_y(self.camera.heading)
+            .set_y(self.camera.vertical_velocity);
+        let moved = self
+            .camera
+            .bounds()
+            .sweep(&velocity, |coordinates| self.is_solid(coordinates));
 
-        let heightmap = Heightmap::new(16.0, 16, 16);
-        let min_height = 1;
-        let height_range = y_height - min_height;
+        self.camera.position = self.camera.position + moved;
 
-        let mut heights = Vec::with_capacity(xz_area);
-        for x in 0..x_width {
-            for z in 0..z_depth {
-                let coordinate = Coordinates(x, 0, z);
-                let xz_position = coordinate.center().xz();
+        // The edges of a limited world act like walls.
+        if let Some(size) = self.size {
+            let half_width = PLAYER_WIDTH / 2.0;
+            let max = size as f32 - half_width;
+            let Vec3(x, y, z) = self.camera.position;
+
+            self.camera.position = Vec3(x.clamp(half_width, max), y, z.clamp(half_width, max));
+        }
 
-                let height = heightmap.height_at(&xz_position);
-                let scaled_height = (height * height_range as f32) as i32 + min_height as i32;
+        // Running into the floor or ceiling stops any vertical movement.
+        if moved.y() != velocity.y() {
+            self.camera.on_ground = velocity.y() < 0.0;
+            self.camera.vertical_velocity = 0.0;
+        } else {
+            self.camera.on_ground = false;
+        }
+    }
 
-                heights.push(scaled_height);
+    /// Updates the blocks that were scheduled since the last update, moving falling blocks with
+    /// nothing beneath them down by one block. Every block is checked before any of them move, so
+    /// a stack of falling blocks comes apart one block at a time from the bottom.
+    fn update_blocks(&mut self) {
+        let block_updates = std::mem::take(&mut self.block_updates);
+
+        let falling_blocks: Vec<(Coordinates, BlockId)> = block_updates
+            .into_iter()
+            .filter_map(|coordinates| {
+                let block = self.get_block(&coordinates);
+                let below = coordinates.offset([0, -1, 0]);
+
+                // Blocks don't fall into chunks that aren't loaded, which includes the chunks
+                // below the bottom of the world.
+                (block.block_type().falls
+                    && self.is_loaded(&below)
+                    && self.get_block(&below).can_fall_into())
+                .then_some((coordinates, block))
+            })
+            .collect();
+
+        for (coordinates, block) in falling_blocks {
+            self.set_block(&coordinates, BlockId::AIR);
+            self.set_block(&coordinates.offset([0, -1, 0]), block);
+        }
+    }
+
+    /// Runs the callbacks of the blocks whose scheduled ticks are due, then the callbacks of a few
+    /// blocks picked at random from each loaded chunk. Everything runs in a fixed order and draws
+    /// from random number generators seeded by the world's seed, so the same world updated the
+    /// same way always ends up the same.
+    fn tick_blocks(&mut self) {
+        let seed = self.generator.options().seed;
+
+        for coordinates in self.tick_scheduler.take_due(self.ticks) {
+            // Ticks in chunks that have since been unloaded are dropped.
+            if !self.is_loaded(&coordinates) {
+                continue;
+            }
+
+            if let Some(callback) = self.get_block(&coordinates).block_type().scheduled_tick {
+                callback(
+                    self,
+                    &coordinates,
+                    &mut tick::block_rng(seed, self.ticks, &coordinates),
+                );
             }
         }
 
-        Self {
-            x_width,
-            z_depth,
-            camera,
-            heights,
+        let mut loaded_chunks: Vec<ChunkCoordinates> = self.chunks.keys().copied().collect();
+        loaded_chunks.sort();
+
+        for chunk_coordinates in loaded_chunks {
+            for coordinates in tick::random_tick_coordinates(seed, self.ticks, &chunk_coordinates) {
+                if let Some(callback) = self.get_block(&coordinates).block_type().random_tick {
+                    callback(
+                        self,
+                        &coordinates,
+                        &mut tick::block_rng(seed, self.ticks, &coordinates),
+                    );
+                }
+            }
         }
     }
 
-    pub(crate) fn update(&mut self) {
-        let actual_velocity = self.camera.velocity.rotate_y(self.camera.heading);
-        self.camera.position = self.camera.position + actual_velocity;
-    }
-
-    pub(crate) fn visible_block_positions(&self) -> impl Iterator<Item = Vec3> + '_ {
-        let x_width = self.x_width as i32;
-        let z_depth = self.z_depth as i32;
-
-        (0..z_depth).flat_map(move |z| {
-            (0..x_width).flat_map(move |x| {
-                let y_max = self.height_at(x, z);
-
-                let min_neighbor_height = [
-                    self.height_at(x + 1, z),
-                    self.height_at(x - 1, z),
-                    self.height_at(x, z + 1),
-                    self.height_at(x, z - 1),
-                ]
-                .into_iter()
-                .min()
-                .unwrap();
+    /// Asks for the block at the given coordinates to be ticked after the given number of ticks.
+    pub(crate) fn schedule_tick(&mut self, coordinates: &Coordinates, delay: u64) {
+        self.tick_scheduler
+            .schedule(coordinates, self.ticks + delay.max(1));
+    }
+
+    /// Runs a step of the fluid simulation. Every change is worked out before any of them are
+    /// made, so the result doesn't depend on the order that the blocks are visited in.
+    fn update_fluids(&mut self) {
+        let fluid_updates = std::mem::take(&mut self.fluid_updates);
+
+        // Blocks in chunks that aren't loaded are dropped, since there's nothing to flow into.
+        let changes: Vec<(Coordinates, BlockId)> = fluid_updates
+            .into_iter()
+            .filter(|coordinates| self.is_loaded(coordinates))
+            .filter_map(|coordinates| {
+                let block = fluid::next_block(&coordinates, |c| self.get_block(c));
+                (block != self.get_block(&coordinates)).then_some((coordinates, block))
+            })
+            .collect();
+
+        for (coordinates, block) in changes {
+            self.set_block(&coordinates, block);
+        }
+    }
+
+    /// Switches between walking and flying.
+    pub(crate) fn toggle_movement_mode(&mut self) -> MovementMode {
+        self.movement_mode = match self.movement_mode {
+            MovementMode::Walking => MovementMode::Flying,
+            MovementMode::Flying => MovementMode::Walking,
+        };
+
+        self.camera.vertical_velocity = 0.0;
+        self.camera.on_ground = false;
+        self.movement_mode
+    }
+
+    /// Requests the closest missing columns within the view distance of the camera, and unloads
+    /// any columns that are now too far away.
+    fn stream_columns(&mut self) {
+        let camera_column = ColumnCoordinates::containing(&self.camera.position);
 
-                let y_min = y_max.min(min_neighbor_height);
+        let far_columns: Vec<ColumnCoordinates> = self
+            .loaded_columns
+            .iter()
+            .filter(|column| !self.is_column_in_range(column))
+            .copied()
+            .collect();
+
+        for column in far_columns {
+            self.unload_column(&column);
+        }
 
-                (y_min..=y_max).map(move |y| Vec3(x as f32, y as f32, z as f32))
+        let mut missing_columns: Vec<ColumnCoordinates> = camera_column
+            .within_distance(self.view_distance)
+            .filter(|column| {
+                self.is_column_in_world(column)
+                    && !self.loaded_columns.contains(column)
+                    && !self.pending_columns.contains(column)
+                    && !self.saving_columns.contains(column)
             })
-        })
+            .collect();
+        missing_columns.sort_by_key(|column| column.distance_squared(&camera_column));
+
+        let num_requests = MAX_PENDING_COLUMNS.saturating_sub(self.pending_columns.len());
+
+        for column in missing_columns.into_iter().take(num_requests) {
+            self.pending_columns.insert(column);
+            self.workers.submit(Job::GenerateColumn(column));
+        }
+    }
+
+    /// Columns are only unloaded once they're a column past the view distance, so that moving
+    /// back and forth over a chunk border doesn't keep loading and unloading the same columns.
+    fn is_column_in_range(&self, column: &ColumnCoordinates) -> bool {
+        let camera_column = ColumnCoordinates::containing(&self.camera.position);
+        let unload_distance = self.view_distance + 1;
+
+        column.distance_squared(&camera_column) <= unload_distance.pow(2)
+    }
+
+    fn is_column_in_world(&self, column: &ColumnCoordinates) -> bool {
+        let Some(size) = self.size else {
+            return true;
+        };
+
+        let num_columns = size / CHUNK_SIZE;
+        let ColumnCoordinates(x, z) = *column;
+
+        (0..num_columns).contains(&x) && (0..num_columns).contains(&z)
     }
 
-    fn height_at(&self, x: i32, z: i32) -> i32 {
-        if x < 0 || x >= self.x_width as i32 || z < 0 || z >= self.z_depth as i32 {
-            0
+    fn receive_finished_jobs(&mut self) {
+        let results: Vec<JobResult> = self.workers.finished_jobs().collect();
+
+        for result in results {
+            match result {
+                JobResult::ColumnGenerated(column, chunks) => {
+                    self.pending_columns.remove(&column);
+
+                    // The camera may have moved away while the column was being generated.
+                    if self.is_column_in_range(&column) {
+                        self.load_column(&column, chunks);
+                    }
+                }
+                JobResult::ColumnSaved(column, result) => {
+                    self.saving_columns.remove(&column);
+
+                    // If the save failed, the chunks are kept to be saved along with the rest of
+                    // the world, or to be used again if the column is loaded again first.
+                    match result {
+                        Ok(()) => {
+                            for y in 0..self.generator.chunks_per_column() {
+                                let chunk_coordinates = column.chunk(y);
+                                if self.unsaved_chunks.remove(&chunk_coordinates).is_some() {
+                                    self.modified_chunks.remove(&chunk_coordinates);
+                                }
+                            }
+                        }
+                        Err(error) => eprintln!("failed to save column {:?}: {}", column, error),
+                    }
+                }
+                JobResult::ChunkMeshed(chunk_coordinates, revision, mesh) => {
+                    if self.mesh_revisions.get(&chunk_coordinates) == Some(&revision) {
+                        self.mesh_revisions.remove(&chunk_coordinates);
+                        self.finished_meshes.insert(chunk_coordinates, mesh);
+                    }
+                }
+            }
+        }
+    }
+
+    fn load_column(&mut self, column: &ColumnCoordinates, chunks: Vec<(ChunkCoordinates, Chunk)>) {
+        for (chunk_coordinates, chunk) in chunks {
+            let chunk = self
+                .unsaved_chunks
+                .remove(&chunk_coordinates)
+                .unwrap_or_else(|| Arc::new(chunk));
+
+            // Caves can leave falling blocks hanging over nothing, so every falling block is
+            // updated once it's loaded to give it the chance to fall.
+            let falling_blocks = chunk_coordinates
+                .block_coordinates()
+                .filter(|coordinates| chunk.get_block(coordinates).block_type().falls);
+            self.block_updates.extend(falling_blocks);
+
+            self.chunks.insert(chunk_coordinates, chunk);
+            self.mark_chunk_and_neighbors_changed(&chunk_coordinates);
+        }
+
+        self.light_column(column);
+        self.loaded_columns.insert(*column);
+    }
+
+    /// Lights up a column that was just loaded. Sunlight shines down through the column from the
+    /// top of the world until it reaches a block that isn't transparent, blocks that give off
+    /// light are lit, and the light at the edges of the columns around it spreads in. All of that
+    /// light then spreads out through the column and beyond.
+    fn light_column(&mut self, column: &ColumnCoordinates) {
+        let Coordinates(x0, _, z0) = column.chunk(0).origin();
+        let top = self.generator.chunks_per_column() * CHUNK_SIZE - 1;
+        let mut sky_queue = VecDeque::new();
+        let mut block_queue = VecDeque::new();
+
+        // The lowest block that sunlight shines straight down to at each position in the column,
+        // indexed by X and then Z, and whether the sunlight has been blocked there yet.
+        let mut sky_bottoms = [[top + 1; CHUNK_SIZE as usize]; CHUNK_SIZE as usize];
+        let mut blocked = [[false; CHUNK_SIZE as usize]; CHUNK_SIZE as usize];
+
+        // The chunks have already been marked as changed, so the sunlight is stored in them
+        // directly rather than through `set_light`. Sunlight passes through chunks that aren't
+        // loaded.
+        for chunk_y in (0..self.generator.chunks_per_column()).rev() {
+            let Some(chunk) = self.chunks.get_mut(&column.chunk(chunk_y)) else {
+                continue;
+            };
+            let chunk = Arc::make_mut(chunk);
+
+            for i in 0..CHUNK_SIZE as usize {
+                for k in 0..CHUNK_SIZE as usize {
+                    for y in (chunk_y * CHUNK_SIZE..(chunk_y + 1) * CHUNK_SIZE).rev() {
+                        if blocked[i][k] {
+                            break;
+                        }
+
+                        let coordinates = Coordinates(x0 + i as i32, y, z0 + k as i32);
+                        if chunk.get_block(&coordinates).block_type().transparent {
+                            chunk.set_light(&coordinates, LightChannel::Sky, MAX_LIGHT);
+                            sky_bottoms[i][k] = y;
+                        } else {
+                            blocked[i][k] = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        // Sunlight only needs to spread sideways from the blocks that have a neighbor it doesn't
+        // shine straight down to. Neighbors in other columns might not have any sunlight yet.
+        for i in 0..CHUNK_SIZE {
+            for k in 0..CHUNK_SIZE {
+                let neighbor_bottom = |di: i32, dk: i32| {
+                    let (i, k) = (i + di, k + dk);
+
+                    if (0..CHUNK_SIZE).contains(&i) && (0..CHUNK_SIZE).contains(&k) {
+                        sky_bottoms[i as usize][k as usize]
+                    } else {
+                        top + 1
+                    }
+                };
+                let highest_neighbor_bottom = [(1, 0), (-1, 0), (0, 1), (0, -1)]
+                    .into_iter()
+                    .map(|(di, dk)| neighbor_bottom(di, dk))
+                    .max()
+                    .unwrap();
+
+                for y in sky_bottoms[i as usize][k as usize]..highest_neighbor_bottom.min(top + 1) {
+                    sky_queue.push_back(Coordinates(x0 + i, y, z0 + k));
+                }
+            }
+        }
+
+        for y in 0..self.generator.chunks_per_column() {
+            let chunk_coordinates = column.chunk(y);
+            let Some(chunk) = self.chunks.get(&chunk_coordinates) else {
+                continue;
+            };
+
+            let emitters: Vec<(Coordinates, u8)> = chunk_coordinates
+                .block_coordinates()
+                .map(|coordinates| {
+                    let emitted =
+                        light::emitted_light(chunk.get_block(&coordinates), LightChannel::Block);
+                    (coordinates, emitted)
+                })
+                .filter(|(_, emitted)| *emitted > 0)
+                .collect();
+
+            for (coordinates, emitted) in emitters {
+                self.set_light(&coordinates, LightChannel::Block, emitted);
+                block_queue.push_back(coordinates);
+            }
+        }
+
+        for y in 0..=top {
+            for i in 0..CHUNK_SIZE {
+                let edges = [
+                    Coordinates(x0 - 1, y, z0 + i),
+                    Coordinates(x0 + CHUNK_SIZE, y, z0 + i),
+                    Coordinates(x0 + i, y, z0 - 1),
+                    Coordinates(x0 + i, y, z0 + CHUNK_SIZE),
+                ];
+
+                for coordinates in edges {
+                    if self.light(&coordinates, LightChannel::Sky) > 0 {
+                        sky_queue.push_back(coordinates);
+                    }
+                    if self.light(&coordinates, LightChannel::Block) > 0 {
+                        block_queue.push_back(coordinates);
+                    }
+                }
+            }
+        }
+
+        light::spread_light(self, LightChannel::Sky, sky_queue);
+        light::spread_light(self, LightChannel::Block, block_queue);
+    }
+
+    fn unload_column(&mut self, column: &ColumnCoordinates) {
+        let mut modified_chunks = Vec::new();
+
+        for y in 0..self.generator.chunks_per_column() {
+            let chunk_coordinates = column.chunk(y);
+
+            if let Some(chunk) = self.chunks.remove(&chunk_coordinates) {
+                if self.modified_chunks.contains(&chunk_coordinates) {
+                    modified_chunks.push((chunk_coordinates, chunk));
+                }
+
+                self.removed_chunks.push(chunk_coordinates);
+                self.changed_chunks.remove(&chunk_coordinates);
+                self.mesh_revisions.remove(&chunk_coordinates);
+                self.finished_meshes.remove(&chunk_coordinates);
+                self.mark_chunk_and_neighbors_changed(&chunk_coordinates);
+            }
+        }
+
+        self.loaded_columns.remove(column);
+
+        if modified_chunks.is_empty() {
+            return;
+        }
+
+        // The modified chunks are saved in the background, so that they can be loaded again
+        // later. Edits to a world that isn't being saved are lost once they're unloaded.
+        if self.storage.is_some() {
+            self.unsaved_chunks.extend(modified_chunks.iter().cloned());
+            self.saving_columns.insert(*column);
+            self.workers
+                .submit(Job::SaveColumn(*column, modified_chunks));
         } else {
-            let index = ((self.x_width as i32 * z) + x) as usize;
-            self.heights[index]
+            for (chunk_coordinates, _) in modified_chunks {
+                self.modified_chunks.remove(&chunk_coordinates);
+            }
+        }
+    }
+
+    /// Saves every modified chunk along with the camera, so that the world can be loaded again
+    /// in the same state. Does nothing if the world isn't being saved.
+    pub(crate) fn save(&mut self) -> Result<(), StorageError> {
+        let Some(storage) = self.storage.clone() else {
+            return Ok(());
+        };
+
+        let modified_chunks: Vec<(ChunkCoordinates, &Chunk)> = self
+            .modified_chunks
+            .iter()
+            .filter_map(|chunk_coordinates| {
+                self.chunks
+                    .get(chunk_coordinates)
+                    .or_else(|| self.unsaved_chunks.get(chunk_coordinates))
+                    .map(|chunk| (*chunk_coordinates, chunk.as_ref()))
+            })
+            .collect();
+
+        storage.save_chunks(&modified_chunks)?;
+        self.modified_chunks.clear();
+        self.unsaved_chunks.clear();
+
+        storage.save_metadata(&WorldMetadata {
+            terrain: self.generator.options().clone(),
+            size: self.size.map(|size| size as u32),
+            camera_position: self.camera.position,
+            camera_heading: self.camera.heading,
+            camera_pitch: self.camera.pitch,
+        })
+    }
+
+    /// Blocks on the border of a chunk can be hidden or revealed by the chunks next to it, so
+    /// those need to be updated too when a chunk is loaded or unloaded.
+    fn mark_chunk_and_neighbors_changed(&mut self, chunk_coordinates: &ChunkCoordinates) {
+        let mut affected_chunks = chunk_coordinates.neighbors().to_vec();
+        affected_chunks.push(*chunk_coordinates);
+
+        for affected_chunk in affected_chunks {
+            if self.chunks.contains_key(&affected_chunk) {
+                self.changed_chunks.insert(affected_chunk);
+            }
+        }
+    }
+
+    /// Starts a mesh job for every chunk that changed since the last update.
+    fn mesh_changed_chunks(&mut self) {
+        let changed_chunks: Vec<ChunkCoordinates> = self.changed_chunks.drain().collect();
+
+        for chunk_coordinates in changed_chunks {
+            let revision = self.next_mesh_revision;
+            self.next_mesh_revision += 1;
+            self.mesh_revisions.insert(chunk_coordinates, revision);
+
+            let neighborhood = self.neighborhood(&chunk_coordinates);
+            self.workers
+                .submit(Job::MeshChunk(revision, neighborhood, self.meshing_mode));
+        }
+    }
+
+    /// Switches between naive and greedy meshing, and rebuilds the mesh of every loaded chunk so
+    /// the two can be compared on the same terrain.
+    pub(crate) fn toggle_meshing_mode(&mut self) -> MeshingMode {
+        self.meshing_mode = match self.meshing_mode {
+            MeshingMode::Naive => MeshingMode::Greedy,
+            MeshingMode::Greedy => MeshingMode::Naive,
+        };
+
+        self.changed_chunks.extend(self.chunks.keys().copied());
+        self.meshing_mode
+    }
+
+    /// Returns every chunk that was unloaded since the last call, along with the meshes of up to
+    /// `max_changed_chunks` of the closest chunks that finished meshing. The rest of the
+    /// meshes are kept for later calls, which limits how much the renderer has to upload at once.
+    pub(crate) fn take_chunk_updates(&mut self, max_changed_chunks: usize) -> Vec<ChunkUpdate> {
+        let mut updates: Vec<ChunkUpdate> = self
+            .removed_chunks
+            .drain(..)
+            .map(ChunkUpdate::Removed)
+            .collect();
+
+        let camera_chunk = Coordinates::containing(&self.camera.position).chunk();
+        let mut finished_chunks: Vec<ChunkCoordinates> =
+            self.finished_meshes.keys().copied().collect();
+        finished_chunks
+            .sort_by_key(|chunk_coordinates| chunk_coordinates.distance_squared(&camera_chunk));
+
+        for chunk_coordinates in finished_chunks.into_iter().take(max_changed_chunks) {
+            if let Some(mesh) = self.finished_meshes.remove(&chunk_coordinates) {
+                updates.push(ChunkUpdate::Changed(chunk_coordinates, mesh));
+            }
+        }
+
+        updates
+    }
+
+    /// Returns the chunk along with its loaded neighbors.
+    pub(crate) fn neighborhood(&self, chunk_coordinates: &ChunkCoordinates) -> ChunkNeighborhood {
+        let ChunkCoordinates(x0, y0, z0) = *chunk_coordinates;
+        let mut chunks = Vec::with_capacity(27);
+
+        for y in (y0 - 1)..=(y0 + 1) {
+            for z in (z0 - 1)..=(z0 + 1) {
+                for x in (x0 - 1)..=(x0 + 1) {
+                    chunks.push(self.chunks.get(&ChunkCoordinates(x, y, z)).cloned());
+                }
+            }
+        }
+
+        ChunkNeighborhood {
+            center: *chunk_coordinates,
+            chunks,
+        }
+    }
+
+    /// Returns the block at the given world coordinates. Blocks in chunks that are not loaded are
+    /// treated as air.
+    pub(crate) fn get_block(&self, coordinates: &Coordinates) -> BlockId {
+        match self.chunks.get(&coordinates.chunk()) {
+            Some(chunk) => chunk.get_block(coordinates),
+            None => BlockId::AIR,
+        }
+    }
+
+    /// Whether the chunk that holds the block at the given coordinates is loaded.
+    pub(crate) fn is_loaded(&self, coordinates: &Coordinates) -> bool {
+        self.chunks.contains_key(&coordinates.chunk())
+    }
+
+    fn is_solid(&self, coordinates: &Coordinates) -> bool {
+        self.get_block(coordinates).block_type().solid
+    }
+
+    /// Replaces the block at the given world coordinates, creating an empty chunk to hold it if
+    /// one doesn't exist yet.
+    pub(crate) fn set_block(&mut self, coordinates: &Coordinates, block: BlockId) {
+        let chunk = self
+            .chunks
+            .entry(coordinates.chunk())
+            .or_insert_with(|| Arc::new(Chunk::empty()));
+
+        // Mesh jobs may still be reading the old version of the chunk, in which case the chunk is
+        // copied rather than being modified underneath them.
+        let chunk = Arc::make_mut(chunk);
+        let old_block = chunk.get_block(coordinates);
+        chunk.set_block(coordinates, block);
+
+        let (old_type, new_type) = (old_block.block_type(), block.block_type());
+        if old_type.transparent != new_type.transparent
+            || old_type.light_emission != new_type.light_emission
+        {
+            light::update_light(self, coordinates);
+        }
+
+        self.modified_chunks.insert(coordinates.chunk());
+
+        self.changed_chunks.insert(coordinates.chunk());
+        self.block_updates.insert(*coordinates);
+        self.fluid_updates.insert(*coordinates);
+
+        for neighbor in coordinates.neighbors().iter() {
+            if self.chunks.contains_key(&neighbor.chunk()) {
+                self.changed_chunks.insert(neighbor.chunk());
+            }
+
+            self.block_updates.insert(*neighbor);
+            self.fluid_updates.insert(*neighbor);
+        }
+    }
+
+    /// Follows a ray from `origin` in the given direction, and returns the first solid block it
+    /// passes through within `max_distance` blocks.
+    ///
+    /// This steps through every block along the ray in order, one block boundary at a time, so no
+    /// blocks are skipped however thin a corner the ray clips.
+    pub(crate) fn raycast(
+        &self,
+        origin: &Vec3,
+        direction: &Vec3,
+        max_distance: f32,
+    ) -> Option<RaycastHit> {
+        let origin = [origin.x(), origin.y(), origin.z()];
+        let direction = direction.normalize();
+        let direction = [direction.x(), direction.y(), direction.z()];
+
+        let Coordinates(x, y, z) = Coordinates::containing(&Vec3(origin[0], origin[1], origin[2]));
+        let mut block = [x, y, z];
+
+        // For each axis, the direction to step in, the distance along the ray to the next block
+        // boundary, and the distance along the ray between boundaries.
+        let mut step = [0; 3];
+        let mut next_boundary = [f32::INFINITY; 3];
+        let mut boundary_spacing = [f32::INFINITY; 3];
+
+        for axis in 0..3 {
+            if direction[axis] > 0.0 {
+                step[axis] = 1;
+                next_boundary[axis] = (block[axis] as f32 + 1.0 - origin[axis]) / direction[axis];
+                boundary_spacing[axis] = 1.0 / direction[axis];
+            } else if direction[axis] < 0.0 {
+                step[axis] = -1;
+                next_boundary[axis] = (origin[axis] - block[axis] as f32) / -direction[axis];
+                boundary_spacing[axis] = 1.0 / -direction[axis];
+            }
+        }
+
+        let mut normal = [0; 3];
+        let mut distance = 0.0;
+
+        loop {
+            let coordinates = Coordinates(block[0], block[1], block[2]);
+
+            if self.is_solid(&coordinates) {
+                return Some(RaycastHit {
+                    coordinates,
+                    normal,
+                    distance,
+                });
+            }
+
+            let axis = (0..3)
+                .min_by(|a, b| next_boundary[*a].total_cmp(&next_boundary[*b]))
+                .unwrap();
+
+            distance = next_boundary[axis];
+            if distance > max_distance {
+                return None;
+            }
+
+            block[axis] += step[axis];
+            next_boundary[axis] += boundary_spacing[axis];
+            normal = [0; 3];
+            normal[axis] = -step[axis];
         }
     }
 
+    /// Returns the block that the camera is looking at, if it's within reach.
+    pub(crate) fn target(&self) -> Option<RaycastHit> {
+        self.raycast(&self.camera.position, &self.camera.direction(), MAX_REACH)
+    }
+
+    /// Removes the block that the camera is looking at.
+    pub(crate) fn remove_block_at_target(&mut self) {
+        if let Some(hit) = self.target() {
+            self.set_block(&hit.coordinates, BlockId::AIR);
+        }
+    }
+
+    /// Places the selected block against the face of the block that the camera is looking at.
+    pub(crate) fn place_block_at_target(&mut self) {
+        let Some(hit) = self.target() else {
+            return;
+        };
+
+        // The camera is inside of the block, so there's no face to place against.
+        if hit.normal == [0; 3] {
+            return;
+        }
+
+        let coordinates = hit.coordinates.offset(hit.normal);
+
+        // Blocks can't be placed in chunks that aren't loaded, since they would never be unloaded.
+        if !self.is_loaded(&coordinates) {
+            return;
+        }
+
+        // Walking players can't place blocks inside of themselves, since they'd be stuck.
+        let inside_player = self.movement_mode == MovementMode::Walking
+            && self.camera.bounds().intersects(&Aabb::block(&coordinates));
+
+        if !self.is_solid(&coordinates) && !inside_player {
+            self.set_block(&coordinates, self.selected_block);
+        }
+    }
+
+    /// Returns the biome of the column that the camera is in, along with the column's climate.
+    pub(crate) fn biome_at_camera(&self) -> (&'static Biome, Climate) {
+        let x = self.camera.position.x().floor() as i32;
+        let z = self.camera.position.z().floor() as i32;
+
+        (
+            self.generator.biome_at(x, z),
+            self.generator.climate_at(x, z),
+        )
+    }
+
+    pub(crate) fn select_block(&mut self, block: BlockId) {
+        self.selected_block = block;
+    }
+
     pub(crate) fn start_moving_forward(&mut self) {
-        self.camera.velocity = self.camera.velocity.set_z(MOVE_SPEED);
+        self.camera.movement = self.camera.movement.set_z(1.0);
     }
 
     pub(crate) fn stop_moving_forward(&mut self) {
-        self.camera.velocity = self.camera.velocity.set_z(0.0);
+        self.camera.movement = self.camera.movement.set_z(0.0);
     }
 
     pub(crate) fn start_moving_backward(&mut self) {
-        self.camera.velocity = self.camera.velocity.set_z(-MOVE_SPEED);
+        self.camera.movement = self.camera.movement.set_z(-1.0);
     }
 
     pub(crate) fn stop_moving_backward(&mut self) {
-        self.camera.velocity = self.camera.velocity.set_z(0.0);
+        self.camera.movement = self.camera.movement.set_z(0.0);
     }
 
     pub(crate) fn start_moving_left(&mut self) {
-        self.camera.velocity = self.camera.velocity.set_x(-MOVE_SPEED);
+        self.camera.movement = self.camera.movement.set_x(-1.0);
     }
 
     pub(crate) fn stop_moving_left(&mut self) {
-        self.camera.velocity = self.camera.velocity.set_x(0.0);
+        self.camera.movement = self.camera.movement.set_x(0.0);
     }
 
     pub(crate) fn start_moving_right(&mut self) {
-        self.camera.velocity = self.camera.velocity.set_x(MOVE_SPEED);
+        self.camera.movement = self.camera.movement.set_x(1.0);
     }
 
     pub(crate) fn stop_moving_right(&mut self) {
-        self.camera.velocity = self.camera.velocity.set_x(0.0);
+        self.camera.movement = self.camera.movement.set_x(0.0);
     }
 
     pub(crate) fn start_moving_up(&mut self) {
-        self.camera.velocity = self.camera.velocity.set_y(MOVE_SPEED);
+        self.camera.movement = self.camera.movement.set_y(1.0);
     }
 
     pub(crate) fn stop_moving_up(&mut self) {
-        self.camera.velocity = self.camera.velocity.set_y(0.0);
+        self.camera.movement = self.camera.movement.set_y(0.0);
     }
 
     pub(crate) fn start_moving_down(&mut self) {
-        self.camera.velocity = self.camera.velocity.set_y(-MOVE_SPEED);
+        self.camera.movement = self.camera.movement.set_y(-1.0);
     }
 
     pub(crate) fn stop_moving_down(&mut self) {
-        self.camera.velocity = self.camera.velocity.set_y(0.0);
+        self.camera.movement = self.camera.movement.set_y(0.0);
     }
 
     pub(crate) fn update_camera_direction(&mut self, dx: f32, dy: f32) {
@@ -151,7 +1014,11 @@ impl World {
 
 pub(crate) struct Camera {
     position: Vec3,
-    velocity: Vec3,
+    /// The direction that the player is trying to move in relative to the camera's heading, with
+    /// each component either -1, 0 or 1. Moving up jumps while walking.
+    movement: Vec3,
+    vertical_velocity: f32,
+    on_ground: bool,
     heading: f32,
     pitch: f32,
 }
@@ -161,273 +1028,1025 @@ impl Camera {
         &self.position
     }
 
-    pub(crate) fn heading(&self) -> f32 {
-        self.heading
+    /// Returns the box that the player takes up.
+    fn bounds(&self) -> Aabb {
+        let half_width = PLAYER_WIDTH / 2.0;
+        let min = self.position + Vec3(-half_width, -EYE_HEIGHT, -half_width);
+        let max = self.position + Vec3(half_width, PLAYER_HEIGHT - EYE_HEIGHT, half_width);
+
+        Aabb::new(min, max)
     }
 
-    pub(crate) fn pitch(&self) -> f32 {
-        self.pitch
+    /// Returns a unit vector pointing in the direction the camera is looking. A heading and
+    /// pitch of zero looks along the positive Z axis, and a positive pitch looks downward.
+    pub(crate) fn direction(&self) -> Vec3 {
+        Vec3(
+            self.heading.sin() * self.pitch.cos(),
+            -self.pitch.sin(),
+            self.heading.cos() * self.pitch.cos(),
+        )
     }
-}
 
-/// Describes how elevation varies across the x-z plane.
-///
-/// For now, the heightmap works on local coordinates from 0..CHUNK_SIDE_LENGTH on the X and Z
-/// axis. The returned height must be within 0..CHUNK_SIDE_LENGTH.
-#[derive(Debug)]
-struct Heightmap {
-    cell_size: f32,
-    num_x_cells: u32,
-    num_z_cells: u32,
-    gradients: Vec<Vec2>,
+    /// Returns a unit vector pointing towards the top of the camera's view, at right angles to
+    /// the direction it's looking.
+    pub(crate) fn up(&self) -> Vec3 {
+        Vec3(
+            self.heading.sin() * self.pitch.sin(),
+            self.pitch.cos(),
+            self.heading.cos() * self.pitch.sin(),
+        )
+    }
 }
 
-impl Heightmap {
-    /// Constructs a Perlin noise grid with random gradients.
-    fn new(cell_size: f32, num_x_cells: u32, num_z_cells: u32) -> Self {
-        let mut rng = RandomNumberGenerator::with_seed(32131);
+/// The block found by a raycast.
+#[derive(Debug, PartialEq)]
+pub(crate) struct RaycastHit {
+    pub(crate) coordinates: Coordinates,
+    /// Points out of the face of the block that the ray entered through, or is zero if the ray
+    /// started inside of the block.
+    pub(crate) normal: [i32; 3],
+    /// How far the ray travelled before entering the block.
+    pub(crate) distance: f32,
+}
 
-        let num_values = ((num_z_cells + 1) * (num_x_cells + 1)) as usize;
-        let mut gradients = Vec::with_capacity(num_values);
+impl LightVolume for World {
+    fn block(&self, coordinates: &Coordinates) -> Option<BlockId> {
+        self.chunks
+            .get(&coordinates.chunk())
+            .map(|chunk| chunk.get_block(coordinates))
+    }
 
-        for _ in 0..num_values {
-            let angle = rng.gen_f32() * std::f32::consts::PI * 2.0;
-            gradients.push(Vec2::from_angle(angle));
+    fn light(&self, coordinates: &Coordinates, channel: LightChannel) -> u8 {
+        match self.chunks.get(&coordinates.chunk()) {
+            Some(chunk) => chunk.get_light(coordinates, channel),
+            None => 0,
         }
+    }
 
-        Self {
-            cell_size,
-            num_x_cells,
-            num_z_cells,
-            gradients,
+    fn set_light(&mut self, coordinates: &Coordinates, channel: LightChannel, level: u8) {
+        let Some(chunk) = self.chunks.get_mut(&coordinates.chunk()) else {
+            return;
+        };
+        Arc::make_mut(chunk).set_light(coordinates, channel, level);
+
+        // The light is drawn on the faces of the blocks around it, which can be in the chunks
+        // next to this one if the block is on the edge of its chunk.
+        let chunk_coordinates = coordinates.chunk();
+        self.changed_chunks.insert(chunk_coordinates);
+
+        let Coordinates(x, y, z) = *coordinates;
+        let ChunkCoordinates(chunk_x, chunk_y, chunk_z) = chunk_coordinates;
+        for (local, [dx, dy, dz]) in [
+            (x.rem_euclid(CHUNK_SIZE), [1, 0, 0]),
+            (y.rem_euclid(CHUNK_SIZE), [0, 1, 0]),
+            (z.rem_euclid(CHUNK_SIZE), [0, 0, 1]),
+        ] {
+            let step = match local {
+                0 => -1,
+                _ if local == CHUNK_SIZE - 1 => 1,
+                _ => continue,
+            };
+            let neighbor = ChunkCoordinates(
+                chunk_x + dx * step,
+                chunk_y + dy * step,
+                chunk_z + dz * step,
+            );
+
+            if self.chunks.contains_key(&neighbor) {
+                self.changed_chunks.insert(neighbor);
+            }
         }
     }
+}
 
-    /// Constructs a Perlin noise grid with the given gradients.
-    ///
-    /// The number of gradients must match the number of corners in the Perlin grid. The first
-    /// gradient is used for x_min / z_min in the grid. The next gradient moves along the x-axis
-    /// first until it reaches x_max, then it moves onto the next row in the z axis. This function
-    /// asserts that enough gradients are given to fit all of the grid corners.
-    #[cfg(test)]
-    fn with_gradients(
-        gradients: Vec<Vec2>,
-        cell_size: f32,
-        num_x_cells: u32,
-        num_z_cells: u32,
-    ) -> Self {
-        let expected_num_gradients = (num_x_cells + 1) * (num_z_cells + 1);
+/// The position of a single block in world space.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct Coordinates(pub(crate) i32, pub(crate) i32, pub(crate) i32);
 
-        assert_eq!(
-            gradients.len(),
-            expected_num_gradients as usize,
-            "wrong number of gradients for heightmap"
-        );
+impl Coordinates {
+    /// Returns the coordinates of the block that contains the given position.
+    fn containing(position: &Vec3) -> Self {
+        Self(
+            position.x().floor() as i32,
+            position.y().floor() as i32,
+            position.z().floor() as i32,
+        )
+    }
 
-        Self {
-            cell_size,
-            num_x_cells,
-            num_z_cells,
-            gradients,
-        }
+    pub(crate) fn center(&self) -> Vec3 {
+        let Self(x, y, z) = *self;
+        Vec3(x as f32 + 0.5, y as f32 + 0.5, z as f32 + 0.5)
     }
 
-    fn height_at(&self, xz_position: &Vec2) -> f32 {
-        if self.is_out_of_range(xz_position) {
-            return 0.0;
-        }
+    pub(crate) fn offset(&self, [dx, dy, dz]: [i32; 3]) -> Self {
+        let Self(x, y, z) = *self;
+        Self(x + dx, y + dy, z + dz)
+    }
+
+    pub(crate) fn neighbors(&self) -> [Coordinates; 6] {
+        let Self(x, y, z) = *self;
+
+        [
+            Self(x + 1, y, z),
+            Self(x - 1, y, z),
+            Self(x, y + 1, z),
+            Self(x, y - 1, z),
+            Self(x, y, z + 1),
+            Self(x, y, z - 1),
+        ]
+    }
 
-        let normalized_position = self.normalize_position(xz_position);
+    /// Returns the coordinates of the chunk that contains this block.
+    pub(crate) fn chunk(&self) -> ChunkCoordinates {
+        let Self(x, y, z) = *self;
 
-        let x0z0_height = self.height_at_x0z0(&normalized_position);
-        let x0z1_height = self.height_at_x0z1(&normalized_position);
-        let x1z0_height = self.height_at_x1z0(&normalized_position);
-        let x1z1_height = self.height_at_x1z1(&normalized_position);
+        ChunkCoordinates(
+            x.div_euclid(CHUNK_SIZE),
+            y.div_euclid(CHUNK_SIZE),
+            z.div_euclid(CHUNK_SIZE),
+        )
+    }
 
-        let x_frac = normalized_position.0.fract();
-        let z_frac = normalized_position.1.fract();
+    /// Returns the index of this block within the chunk that contains it.
+    fn chunk_index(&self) -> usize {
+        let Self(x, y, z) = *self;
 
-        let x0_height = math::interpolate(x0z0_height, x0z1_height, z_frac);
-        let x1_height = math::interpolate(x1z0_height, x1z1_height, z_frac);
+        let local_x = x.rem_euclid(CHUNK_SIZE);
+        let local_y = y.rem_euclid(CHUNK_SIZE);
+        let local_z = z.rem_euclid(CHUNK_SIZE);
 
-        let height = math::interpolate(x0_height, x1_height, x_frac);
-        (height + 1.0) * 0.5
+        ((local_y * CHUNK_SIZE + local_z) * CHUNK_SIZE + local_x) as usize
     }
+}
 
-    fn is_out_of_range(&self, xz_position: &Vec2) -> bool {
-        let Vec2(x, z) = *xz_position;
+/// The position of a chunk, measured in chunks rather than blocks. The chunk at (0, 0, 0) holds
+/// the blocks from (0, 0, 0) up to (but not including) (CHUNK_SIZE, CHUNK_SIZE, CHUNK_SIZE).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct ChunkCoordinates(pub(crate) i32, pub(crate) i32, pub(crate) i32);
 
-        x < self.min_x() || x >= self.max_x() || z < self.min_z() || z >= self.max_z()
+impl ChunkCoordinates {
+    pub(crate) fn origin(&self) -> Coordinates {
+        let Self(x, y, z) = *self;
+        Coordinates(x * CHUNK_SIZE, y * CHUNK_SIZE, z * CHUNK_SIZE)
     }
 
-    fn normalize_position(&self, xz_position: &Vec2) -> Vec2 {
-        *xz_position / self.cell_size
+    /// Iterates over the world coordinates of every block within this chunk.
+    pub(crate) fn block_coordinates(&self) -> impl Iterator<Item = Coordinates> {
+        let Coordinates(x0, y0, z0) = self.origin();
+
+        (0..CHUNK_SIZE).flat_map(move |y| {
+            (0..CHUNK_SIZE).flat_map(move |z| {
+                (0..CHUNK_SIZE).map(move |x| Coordinates(x0 + x, y0 + y, z0 + z))
+            })
+        })
     }
 
-    fn min_x(&self) -> f32 {
-        0.0
+    fn distance_squared(&self, other: &ChunkCoordinates) -> i32 {
+        (self.0 - other.0).pow(2) + (self.1 - other.1).pow(2) + (self.2 - other.2).pow(2)
     }
 
-    fn max_x(&self) -> f32 {
-        self.cell_size * self.num_x_cells as f32
+    fn neighbors(&self) -> [ChunkCoordinates; 6] {
+        let Self(x, y, z) = *self;
+
+        [
+            Self(x + 1, y, z),
+            Self(x - 1, y, z),
+            Self(x, y + 1, z),
+            Self(x, y - 1, z),
+            Self(x, y, z + 1),
+            Self(x, y, z - 1),
+        ]
     }
+}
+
+/// A chunk along with the 26 chunks that surround it, which is everything needed to work out
+/// which of the chunk's block faces are visible. The chunks are shared with the world, so this can
+/// be handed to another thread without copying any blocks.
+pub(crate) struct ChunkNeighborhood {
+    center: ChunkCoordinates,
+    /// Ordered by Y, then Z, then X, with `None` for any chunks that aren't loaded.
+    chunks: Vec<Option<Arc<Chunk>>>,
+}
 
-    fn min_z(&self) -> f32 {
-        0.0
+impl ChunkNeighborhood {
+    pub(crate) fn center(&self) -> ChunkCoordinates {
+        self.center
     }
 
-    fn max_z(&self) -> f32 {
-        self.cell_size * self.num_z_cells as f32
+    /// Returns the block at the given world coordinates. Blocks outside of the neighborhood or in
+    /// chunks that aren't loaded are treated as air.
+    pub(crate) fn get_block(&self, coordinates: &Coordinates) -> BlockId {
+        let chunk = self
+            .chunk_index(&coordinates.chunk())
+            .and_then(|index| self.chunks[index].as_ref());
+
+        match chunk {
+            Some(chunk) => chunk.get_block(coordinates),
+            None => BlockId::AIR,
+        }
     }
 
-    fn height_at_x0z0(&self, normalized_position: &Vec2) -> f32 {
-        let Vec2(x, z) = *normalized_position;
+    /// Returns the light at the given world coordinates. Blocks outside of the neighborhood or in
+    /// chunks that aren't loaded are treated as dark.
+    pub(crate) fn get_light(&self, coordinates: &Coordinates, channel: LightChannel) -> u8 {
+        let chunk = self
+            .chunk_index(&coordinates.chunk())
+            .and_then(|index| self.chunks[index].as_ref());
 
-        let xi = x as usize;
-        let zi = z as usize;
+        match chunk {
+            Some(chunk) => chunk.get_light(coordinates, channel),
+            None => 0,
+        }
+    }
+
+    /// Sets the light at the given world coordinates, which must be in a chunk that has blocks.
+    #[cfg(test)]
+    pub(crate) fn set_light(
+        &mut self,
+        coordinates: &Coordinates,
+        channel: LightChannel,
+        level: u8,
+    ) {
+        let index = self
+            .chunk_index(&coordinates.chunk())
+            .expect("block is outside of the neighborhood");
+        let chunk = self.chunks[index]
+            .as_mut()
+            .expect("block is in a chunk without any blocks");
+
+        Arc::make_mut(chunk).set_light(coordinates, channel, level);
+    }
 
-        let corner_position = Vec2(xi as f32, zi as f32);
-        let gradient = self.gradient_at_index(xi, zi);
+    fn chunk_index(&self, chunk_coordinates: &ChunkCoordinates) -> Option<usize> {
+        let ChunkCoordinates(x, y, z) = *chunk_coordinates;
+        let ChunkCoordinates(x0, y0, z0) = self.center;
+        let (dx, dy, dz) = (x - x0, y - y0, z - z0);
 
-        let offset = *normalized_position - corner_position;
-        gradient.dot(&offset)
+        if dx.abs() > 1 || dy.abs() > 1 || dz.abs() > 1 {
+            None
+        } else {
+            Some(((dy + 1) * 9 + (dz + 1) * 3 + (dx + 1)) as usize)
+        }
     }
 
-    fn height_at_x1z0(&self, normalized_position: &Vec2) -> f32 {
-        let Vec2(x, z) = *normalized_position;
+    /// Builds a neighborhood around the given chunk containing only the given blocks.
+    #[cfg(test)]
+    pub(crate) fn with_blocks(center: ChunkCoordinates, blocks: &[(Coordinates, BlockId)]) -> Self {
+        let mut chunks: Vec<Option<Chunk>> = vec![None; 27];
+        let mut neighborhood = Self {
+            center,
+            chunks: Vec::new(),
+        };
 
-        let xi = x as usize + 1;
-        let zi = z as usize;
+        for (coordinates, block) in blocks.iter() {
+            let index = neighborhood
+                .chunk_index(&coordinates.chunk())
+                .expect("block is outside of the neighborhood");
 
-        let corner_position = Vec2(xi as f32, zi as f32);
-        let gradient = self.gradient_at_index(xi, zi);
+            chunks[index]
+                .get_or_insert_with(Chunk::empty)
+                .set_block(coordinates, *block);
+        }
 
-        let offset = *normalized_position - corner_position;
-        gradient.dot(&offset)
+        neighborhood.chunks = chunks
+            .into_iter()
+            .map(|chunk| chunk.map(Arc::new))
+            .collect();
+        neighborhood
     }
+}
+
+/// The position of a vertical stack of chunks, measured in chunks along the X and Z axes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct ColumnCoordinates(pub(crate) i32, pub(crate) i32);
 
-    fn height_at_x0z1(&self, normalized_position: &Vec2) -> f32 {
-        let Vec2(x, z) = *normalized_position;
+impl ColumnCoordinates {
+    fn containing(position: &Vec3) -> Self {
+        let chunk_size = CHUNK_SIZE as f32;
 
-        let xi = x as usize;
-        let zi = z as usize + 1;
+        Self(
+            (position.x() / chunk_size).floor() as i32,
+            (position.z() / chunk_size).floor() as i32,
+        )
+    }
 
-        let corner_position = Vec2(xi as f32, zi as f32);
-        let gradient = self.gradient_at_index(xi, zi);
+    /// Returns the coordinates of the chunk at the given height within this column.
+    pub(crate) fn chunk(&self, y: i32) -> ChunkCoordinates {
+        let Self(x, z) = *self;
+        ChunkCoordinates(x, y, z)
+    }
 
-        let offset = *normalized_position - corner_position;
-        gradient.dot(&offset)
+    fn distance_squared(&self, other: &ColumnCoordinates) -> i32 {
+        (self.0 - other.0).pow(2) + (self.1 - other.1).pow(2)
     }
 
-    fn height_at_x1z1(&self, normalized_position: &Vec2) -> f32 {
-        let Vec2(x, z) = *normalized_position;
+    /// Iterates over every column within the given distance of this column (including itself).
+    fn within_distance(&self, distance: i32) -> impl Iterator<Item = ColumnCoordinates> {
+        let center = *self;
+        let Self(x0, z0) = center;
 
-        let xi = x as usize + 1;
-        let zi = z as usize + 1;
+        ((x0 - distance)..=(x0 + distance))
+            .flat_map(move |x| ((z0 - distance)..=(z0 + distance)).map(move |z| Self(x, z)))
+            .filter(move |column| column.distance_squared(&center) <= distance.pow(2))
+    }
+}
 
-        let corner_position = Vec2(xi as f32, zi as f32);
-        let gradient = self.gradient_at_index(xi, zi);
+/// A fixed-size cube of blocks. Blocks are addressed with world coordinates, and only the
+/// portion of the coordinates that falls within the chunk is used.
+#[derive(Debug, Clone)]
+pub(crate) struct Chunk {
+    blocks: Vec<BlockId>,
+    /// The level of each kind of light in each block, with the sky light in the upper four bits
+    /// and the block light in the lower four. Light isn't saved, since it can always be worked out
+    /// again from the blocks.
+    light: Vec<u8>,
+}
+
+impl Chunk {
+    pub(crate) fn empty() -> Self {
+        Self {
+            blocks: vec![BlockId::AIR; BLOCKS_PER_CHUNK],
+            light: vec![0; BLOCKS_PER_CHUNK],
+        }
+    }
 
-        let offset = *normalized_position - corner_position;
-        gradient.dot(&offset)
+    pub(crate) fn get_block(&self, coordinates: &Coordinates) -> BlockId {
+        self.blocks[coordinates.chunk_index()]
     }
 
-    fn gradient_at_index(&self, xi: usize, zi: usize) -> Vec2 {
-        let i = zi * self.num_x_cells as usize + xi;
-        self.gradients[i]
+    pub(crate) fn set_block(&mut self, coordinates: &Coordinates, block: BlockId) {
+        self.blocks[coordinates.chunk_index()] = block;
     }
-}
 
-struct Coordinates(u32, u32, u32);
+    pub(crate) fn get_light(&self, coordinates: &Coordinates, channel: LightChannel) -> u8 {
+        let light = self.light[coordinates.chunk_index()];
 
-impl Coordinates {
-    fn center(&self) -> Vec3 {
-        let Self(x, y, z) = *self;
-        Vec3(x as f32 + 0.5, y as f32 + 0.5, z as f32 + 0.5)
+        match channel {
+            LightChannel::Sky => light >> 4,
+            LightChannel::Block => light & 0x0f,
+        }
+    }
+
+    pub(crate) fn set_light(
+        &mut self,
+        coordinates: &Coordinates,
+        channel: LightChannel,
+        level: u8,
+    ) {
+        let light = &mut self.light[coordinates.chunk_index()];
+
+        *light = match channel {
+            LightChannel::Sky => (*light & 0x0f) | (level << 4),
+            LightChannel::Block => (*light & 0xf0) | level,
+        };
+    }
+
+    /// Returns the registry index of every block in the chunk, in the same order that they're
+    /// stored in.
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        self.blocks.iter().map(|block| block.index()).collect()
+    }
+
+    /// Rebuilds a chunk from the output of `to_bytes`, or returns `None` if the bytes don't
+    /// describe a valid chunk.
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != BLOCKS_PER_CHUNK {
+            return None;
+        }
+
+        let blocks = bytes
+            .iter()
+            .map(|index| BlockId::from_index(*index))
+            .collect::<Option<Vec<BlockId>>>()?;
+
+        Some(Self {
+            blocks,
+            light: vec![0; BLOCKS_PER_CHUNK],
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::f32::consts::*;
-
     use super::*;
+    use crate::{block::MAX_FLUID_LEVEL, storage::TestDirectory, structure::TREE};
 
     #[test]
-    fn perlin_noise_single_grid_cell() {
-        // Create a 2D Perlin noise grid with one cell and four gradients (one for each corner). In
-        // this example, all gradients are unit vectors that point along either the X or Z axis.
-        let gradients = vec![
-            Vec2::from_angle(0.0),
-            Vec2::from_angle(-PI),
-            Vec2::from_angle(FRAC_PI_2),
-            Vec2::from_angle(-FRAC_PI_2),
-        ];
-
-        let heightmap = Heightmap::with_gradients(gradients, 1.0, 1, 1);
+    fn get_and_set_blocks_across_chunks() {
+        let mut world = test_world();
 
         let examples = [
-            // Always zero at the grid corner
-            (Vec2(0.0, 0.0), 0.0),
-            // Halfway between two corners on X axis, both with gradients facing inward (max strength)
-            (Vec2(0.5, 0.0), 0.5),
-            // Strength drops as approaching either corner. Should drop equally on both sides.
-            (Vec2(0.25, 0.0), 0.375),
-            (Vec2(0.75, 0.0), 0.375),
-            // Along the Z axis, gradient faces away so should be negative
-            (Vec2(0.0, 0.5), -0.25),
-            (Vec2(0.0, 0.25), -0.1875),
-            (Vec2(0.0, 0.75), -0.1875),
-            // In the middle. The two gradients along Z=0 contribute 0.5, and the two gradients at
-            // Z=1 are facing opposite directions and cancel each other out (so 0.0), which results
-            // in an interpolated value of 0.25.
-            (Vec2(0.5, 0.5), 0.25),
+            Coordinates(0, 0, 0),
+            Coordinates(CHUNK_SIZE - 1, 0, 0),
+            Coordinates(CHUNK_SIZE, 0, 0),
+            Coordinates(-1, -1, -1),
+            Coordinates(-CHUNK_SIZE, 3, 2 * CHUNK_SIZE + 5),
         ];
 
-        for (position, expected) in examples.into_iter() {
-            let actual = heightmap.height_at(&position);
+        for coordinates in examples.iter() {
+            assert_eq!(world.get_block(coordinates), BlockId::AIR);
+            world.set_block(coordinates, BlockId::STONE);
+        }
+
+        for coordinates in examples.iter() {
+            assert_eq!(world.get_block(coordinates), BlockId::STONE);
 
-            assert!((actual - expected).abs() < 0.00001);
+            for neighbor in coordinates.neighbors().iter() {
+                if !examples.contains(neighbor) {
+                    assert_eq!(world.get_block(neighbor), BlockId::AIR);
+                }
+            }
         }
     }
 
     #[test]
-    fn perlin_noise_varying_cell_size() {
-        let small_cell_heightmap = Heightmap::new(1.0, 2, 2);
-        let big_cell_heightmap = Heightmap::new(16.0, 2, 2);
+    fn enclosed_faces_are_not_visible() {
+        let mut world = test_world();
+
+        // A 3x3x3 cube of blocks straddling a chunk boundary.
+        for x in -1..=1 {
+            for y in 1..=3 {
+                for z in -1..=1 {
+                    world.set_block(&Coordinates(x, y, z), BlockId::STONE);
+                }
+            }
+        }
 
-        // Positions for the small cell and big cell heightmaps. The left and right values should
-        // return the same height value since they're in the same spot relative to the size of the
-        // grid.
-        let examples = [
-            (Vec2(0.0, 0.0), Vec2(0.0, 0.0)),
-            (Vec2(1.5, 0.5), Vec2(24.0, 8.0)),
-            (Vec2(1.0, 1.0), Vec2(16.0, 16.0)),
-        ];
+        // Faces between the blocks are hidden, even where they're in different chunks, leaving 9
+        // faces on each side of the cube.
+        assert_eq!(changed_face_count(&mut world), 9 * 6);
+    }
 
-        for (small_position, big_position) in examples.into_iter() {
-            let small_cell_height = small_cell_heightmap.height_at(&small_position);
-            let big_cell_height = big_cell_heightmap.height_at(&big_position);
+    #[test]
+    fn faces_behind_transparent_blocks_are_visible() {
+        let mut world = test_world();
+
+        for x in -1..=1 {
+            for y in 1..=3 {
+                for z in -1..=1 {
+                    world.set_block(&Coordinates(x, y, z), BlockId::STONE);
+                }
+            }
+        }
+
+        // Replacing the middle of the top layer with leaves exposes the top of the middle block
+        // and the sides of the four stone blocks around the leaves. The leaves only add their top
+        // face, since their other neighbors are opaque.
+        world.set_block(&Coordinates(0, 3, 0), BlockId::LEAVES);
+
+        assert_eq!(changed_face_count(&mut world), 9 * 6 + 1 + 4);
+    }
 
-            assert_eq!(small_cell_height, big_cell_height);
+    #[test]
+    fn raycasts_stop_at_the_first_solid_block() {
+        let mut world = test_world();
+        world.set_block(&Coordinates(0, 0, 0), BlockId::STONE);
+        world.set_block(&Coordinates(0, 0, 3), BlockId::STONE);
+        world.set_block(&Coordinates(0, 0, 5), BlockId::STONE);
+        world.set_block(&Coordinates(0, 0, 4), BlockId::WATER);
+
+        let origin = Vec3(0.5, 0.5, 4.5);
+
+        // Water doesn't stop the ray, so it passes into the blocks on either side.
+        let hit = world.raycast(&origin, &Vec3(0.0, 0.0, 1.0), 8.0).unwrap();
+        assert_eq!(hit.coordinates, Coordinates(0, 0, 5));
+        assert_eq!(hit.normal, [0, 0, -1]);
+        assert!((hit.distance - 0.5).abs() < 0.0001);
+
+        let hit = world.raycast(&origin, &Vec3(0.0, 0.0, -2.0), 8.0).unwrap();
+        assert_eq!(hit.coordinates, Coordinates(0, 0, 3));
+        assert_eq!(hit.normal, [0, 0, 1]);
+        assert!((hit.distance - 0.5).abs() < 0.0001);
+
+        // Looking diagonally down at the block from above enters it through the top.
+        let hit = world
+            .raycast(&Vec3(0.5, 3.5, -1.0), &Vec3(0.0, -1.0, 0.5), 8.0)
+            .unwrap();
+        assert_eq!(hit.coordinates, Coordinates(0, 0, 0));
+        assert_eq!(hit.normal, [0, 1, 0]);
+
+        assert_eq!(world.raycast(&origin, &Vec3(1.0, 0.0, 0.0), 8.0), None);
+        assert_eq!(
+            world.raycast(&Vec3(0.5, 10.5, 0.5), &Vec3(0.0, -1.0, 0.0), 8.0),
+            None,
+            "blocks beyond the maximum distance can't be hit"
+        );
+    }
+
+    #[test]
+    fn blocks_are_removed_and_placed_where_the_camera_looks() {
+        let mut world = test_world();
+        world.set_block(&Coordinates(0, 0, 0), BlockId::STONE);
+        world.set_block(&Coordinates(0, 1, 0), BlockId::DIRT);
+        world.set_block(&Coordinates(-CHUNK_SIZE, 0, 0), BlockId::DIRT);
+        world.set_block(&Coordinates(0, 0, CHUNK_SIZE), BlockId::DIRT);
+        world.changed_chunks.clear();
+
+        // Look straight down at the column of blocks.
+        world.camera.position = Vec3(0.5, 5.5, 0.5);
+        world.camera.pitch = std::f32::consts::FRAC_PI_2;
+
+        world.remove_block_at_target();
+        assert_eq!(world.get_block(&Coordinates(0, 1, 0)), BlockId::AIR);
+        assert_eq!(world.get_block(&Coordinates(0, 0, 0)), BlockId::STONE);
+
+        // Only the chunks touching the removed block need to be meshed again.
+        let expected_chunks =
+            HashSet::from([ChunkCoordinates(0, 0, 0), ChunkCoordinates(-1, 0, 0)]);
+        assert_eq!(world.changed_chunks, expected_chunks);
+
+        world.select_block(BlockId::LAMP);
+        world.place_block_at_target();
+        assert_eq!(world.get_block(&Coordinates(0, 1, 0)), BlockId::LAMP);
+    }
+
+    #[test]
+    fn placed_water_flows_as_the_fluids_update() {
+        let mut world = test_world();
+
+        for x in -3..=3 {
+            for z in -3..=3 {
+                world.set_block(&Coordinates(x, 0, z), BlockId::STONE);
+            }
+        }
+
+        // Placing blocks that aren't next to any water doesn't change anything.
+        world.update_fluids();
+        assert!(world.fluid_updates.is_empty());
+
+        world.set_block(&Coordinates(0, 1, 0), BlockId::WATER);
+        for _ in 0..3 {
+            world.update_fluids();
         }
+
+        assert_eq!(world.get_block(&Coordinates(0, 1, 0)), BlockId::WATER);
+        assert_eq!(world.get_block(&Coordinates(2, 1, 0)), BlockId::water(2));
+        assert_eq!(world.get_block(&Coordinates(0, 1, -3)), BlockId::water(3));
+        assert_eq!(world.get_block(&Coordinates(0, 1, 4)), BlockId::AIR);
+
+        // Taking away the source dries up the water that flowed out of it.
+        world.set_block(&Coordinates(0, 1, 0), BlockId::AIR);
+        for _ in 0..(MAX_FLUID_LEVEL * 2) {
+            world.update_fluids();
+        }
+
+        assert_eq!(world.get_block(&Coordinates(2, 1, 0)), BlockId::AIR);
+        assert!(world.fluid_updates.is_empty());
     }
 
     #[test]
-    fn perlin_noise_outside_of_cell_range() {
-        let heightmap = Heightmap::new(16.0, 2, 2);
+    fn falling_blocks_fall_until_something_holds_them_up() {
+        let mut world = test_world();
+
+        world.set_block(&Coordinates(0, 1, 0), BlockId::STONE);
+        world.set_block(&Coordinates(0, 4, 0), BlockId::SAND);
+        world.set_block(&Coordinates(0, 5, 0), BlockId::GRAVEL);
+        for _ in 0..5 {
+            world.update_blocks();
+        }
 
-        // Positions outside of the grid range should return 0.0 height.
-        let examples = [
-            Vec2(-0.0001, 0.0),
-            Vec2(32.0, 32.0),
-            Vec2(32.0, 0.0),
-            Vec2(16.0, 32.0),
-            Vec2(-0.0001, 10.0),
-        ];
+        assert_eq!(world.get_block(&Coordinates(0, 2, 0)), BlockId::SAND);
+        assert_eq!(world.get_block(&Coordinates(0, 3, 0)), BlockId::GRAVEL);
+        assert_eq!(world.get_block(&Coordinates(0, 4, 0)), BlockId::AIR);
+        assert_eq!(world.get_block(&Coordinates(0, 5, 0)), BlockId::AIR);
+        assert!(world.block_updates.is_empty());
+
+        // Removing the block underneath brings the whole stack down, as far as the bottom of the
+        // world.
+        world.set_block(&Coordinates(0, 1, 0), BlockId::AIR);
+        for _ in 0..5 {
+            world.update_blocks();
+        }
+
+        assert_eq!(world.get_block(&Coordinates(0, 0, 0)), BlockId::SAND);
+        assert_eq!(world.get_block(&Coordinates(0, 1, 0)), BlockId::GRAVEL);
+        assert_eq!(world.get_block(&Coordinates(0, 2, 0)), BlockId::AIR);
+    }
+
+    #[test]
+    fn hanging_blocks_fall_once_their_column_is_loaded() {
+        let mut world = test_world();
+
+        let mut chunk = Chunk::empty();
+        chunk.set_block(&Coordinates(3, 0, 3), BlockId::STONE);
+        chunk.set_block(&Coordinates(3, 8, 3), BlockId::SAND);
+        chunk.set_block(&Coordinates(5, 0, 5), BlockId::SAND);
+        world.load_column(
+            &ColumnCoordinates(0, 0),
+            vec![(ChunkCoordinates(0, 0, 0), chunk)],
+        );
 
-        for position in examples.into_iter() {
-            assert_eq!(heightmap.height_at(&position), 0.0);
+        for _ in 0..10 {
+            world.update_blocks();
         }
+
+        assert_eq!(world.get_block(&Coordinates(3, 1, 3)), BlockId::SAND);
+        assert_eq!(world.get_block(&Coordinates(3, 8, 3)), BlockId::AIR);
+        assert_eq!(world.get_block(&Coordinates(5, 0, 5)), BlockId::SAND);
+    }
+
+    #[test]
+    fn loaded_columns_are_lit_by_the_sky_and_their_lamps() {
+        let mut world = test_world();
+
+        // A stone roof over the column at y = 8, with a lamp underneath it.
+        let mut chunk = Chunk::empty();
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                chunk.set_block(&Coordinates(x, 8, z), BlockId::STONE);
+            }
+        }
+        chunk.set_block(&Coordinates(4, 4, 4), BlockId::LAMP);
+        world.load_column(
+            &ColumnCoordinates(0, 0),
+            vec![(ChunkCoordinates(0, 0, 0), chunk)],
+        );
+
+        assert_eq!(
+            world.light(&Coordinates(4, 9, 4), LightChannel::Sky),
+            MAX_LIGHT
+        );
+        assert_eq!(world.light(&Coordinates(4, 7, 4), LightChannel::Sky), 0);
+        assert_eq!(world.light(&Coordinates(4, 4, 4), LightChannel::Block), 15);
+        assert_eq!(world.light(&Coordinates(4, 4, 7), LightChannel::Block), 12);
+
+        // Breaking through the roof lets the sunlight in, and removing the lamp puts its light
+        // out.
+        world.set_block(&Coordinates(10, 8, 10), BlockId::AIR);
+        world.set_block(&Coordinates(4, 4, 4), BlockId::AIR);
+
+        assert_eq!(
+            world.light(&Coordinates(10, 0, 10), LightChannel::Sky),
+            MAX_LIGHT
+        );
+        assert_eq!(world.light(&Coordinates(10, 0, 7), LightChannel::Sky), 12);
+        assert_eq!(world.light(&Coordinates(4, 4, 7), LightChannel::Block), 0);
+    }
+
+    #[test]
+    fn grass_spreads_to_uncovered_dirt_and_dies_when_covered() {
+        let mut world = test_world();
+
+        for x in 4..=8 {
+            for z in 4..=8 {
+                world.set_block(&Coordinates(x, 3, z), BlockId::DIRT);
+            }
+        }
+        world.set_block(&Coordinates(6, 3, 6), BlockId::GRASS);
+        world.set_block(&Coordinates(8, 4, 8), BlockId::STONE);
+        world.set_block(&Coordinates(8, 4, 7), BlockId::WATER);
+        run_ticks(&mut world, 100_000);
+
+        let grass_count = (4..=8)
+            .flat_map(|x| (4..=8).map(move |z| Coordinates(x, 3, z)))
+            .filter(|coordinates| world.get_block(coordinates) == BlockId::GRASS)
+            .count();
+        assert!(grass_count > 5);
+        assert_eq!(world.get_block(&Coordinates(8, 3, 8)), BlockId::DIRT);
+        assert_eq!(world.get_block(&Coordinates(8, 3, 7)), BlockId::DIRT);
+
+        world.set_block(&Coordinates(6, 4, 6), BlockId::STONE);
+        run_ticks(&mut world, 20_000);
+        assert_eq!(world.get_block(&Coordinates(6, 3, 6)), BlockId::DIRT);
+    }
+
+    #[test]
+    fn leaves_decay_once_cut_off_from_wood() {
+        let mut world = test_world();
+
+        let origin = Coordinates(8, 1, 8);
+        for (offset, block) in TREE.blocks.iter().rev() {
+            world.set_block(&origin.offset(*offset), *block);
+        }
+
+        let count_leaves = |world: &World| {
+            TREE.blocks
+                .iter()
+                .filter(|(offset, _)| world.get_block(&origin.offset(*offset)) == BlockId::LEAVES)
+                .count()
+        };
+        let num_leaves = count_leaves(&world);
+
+        run_ticks(&mut world, 20_000);
+        assert_eq!(count_leaves(&world), num_leaves);
+
+        for y in 0..4 {
+            world.set_block(&origin.offset([0, y, 0]), BlockId::AIR);
+        }
+        run_ticks(&mut world, 5_000);
+        assert_eq!(count_leaves(&world), 0);
+    }
+
+    #[test]
+    fn block_ticks_are_repeatable() {
+        let build_world = || {
+            let mut world = test_world();
+
+            for x in 0..16 {
+                for z in 0..16 {
+                    world.set_block(&Coordinates(x, 0, z), BlockId::DIRT);
+                }
+            }
+            world.set_block(&Coordinates(8, 0, 8), BlockId::GRASS);
+            run_ticks(&mut world, 10_000);
+
+            world
+        };
+
+        let world_a = build_world();
+        let world_b = build_world();
+
+        let grass_a: Vec<Coordinates> = ChunkCoordinates(0, 0, 0)
+            .block_coordinates()
+            .filter(|coordinates| world_a.get_block(coordinates) == BlockId::GRASS)
+            .collect();
+        let grass_b: Vec<Coordinates> = ChunkCoordinates(0, 0, 0)
+            .block_coordinates()
+            .filter(|coordinates| world_b.get_block(coordinates) == BlockId::GRASS)
+            .collect();
+
+        assert!(grass_a.len() > 1);
+        assert_eq!(grass_a, grass_b);
+    }
+
+    #[test]
+    fn walking_players_land_on_the_ground_and_jump() {
+        let mut world = test_world();
+
+        for x in -2..=2 {
+            for z in -2..=2 {
+                world.set_block(&Coordinates(x, 4, z), BlockId::STONE);
+            }
+        }
+        world.set_block(&Coordinates(0, 5, 2), BlockId::STONE);
+
+        // Pretend that the column was generated, since the player waits for that before falling.
+        world.loaded_columns.insert(ColumnCoordinates(0, 0));
+        world.camera.position = Vec3(0.5, 20.0, 0.5);
+
+        for _ in 0..100 {
+            world.update();
+        }
+
+        let ground_height = 5.0 + EYE_HEIGHT;
+        assert!(world.camera.on_ground);
+        assert!((world.camera.position.y() - ground_height).abs() < 0.001);
+
+        // Walking into the block in front of the player stops them against it.
+        world.start_moving_forward();
+        for _ in 0..30 {
+            world.update();
+        }
+        world.stop_moving_forward();
+        assert!((world.camera.position.z() - (2.0 - PLAYER_WIDTH / 2.0)).abs() < 0.001);
+
+        // A jump is high enough to get on top of a block.
+        world.start_moving_up();
+        world.update();
+        world.stop_moving_up();
+
+        let mut highest_position = world.camera.position.y();
+        while !world.camera.on_ground {
+            world.update();
+            highest_position = highest_position.max(world.camera.position.y());
+        }
+
+        assert!(highest_position > ground_height + 1.0);
+        assert!((world.camera.position.y() - ground_height).abs() < 0.001);
+    }
+
+    #[test]
+    fn edits_are_kept_when_the_world_is_saved_and_loaded() {
+        let directory = TestDirectory::new("edits_are_kept_when_the_world_is_saved_and_loaded");
+        let open_world = || {
+            let storage = WorldStorage::open(&directory.0).unwrap();
+            let options = WorldOptions {
+                view_distance: 1,
+                ..Default::default()
+            };
+            World::load(storage, options).unwrap()
+        };
+
+        let mut world = open_world();
+        world.movement_mode = MovementMode::Flying;
+        settle(&mut world);
+
+        let edited_block = Coordinates(3, 40, 3);
+        world.set_block(&edited_block, BlockId::LAMP);
+
+        // Moving away unloads the edited column, which saves it.
+        let starting_position = world.camera.position;
+        world.camera.position = Vec3(10.0 * CHUNK_SIZE as f32, 40.0, 0.0);
+        settle(&mut world);
+        assert_eq!(world.get_block(&edited_block), BlockId::AIR);
+
+        world.camera.position = starting_position;
+        settle(&mut world);
+        assert_eq!(world.get_block(&edited_block), BlockId::LAMP);
+
+        world.camera.heading = 1.5;
+        world.save().unwrap();
+        drop(world);
+
+        let mut world = open_world();
+        world.movement_mode = MovementMode::Flying;
+        assert_eq!(world.camera.position, starting_position);
+        assert_eq!(world.camera.heading, 1.5);
+
+        settle(&mut world);
+        assert_eq!(world.get_block(&edited_block), BlockId::LAMP);
+    }
+
+    #[test]
+    fn edits_are_kept_when_saving_an_unloaded_column_fails() {
+        let directory = TestDirectory::new("edits_are_kept_when_saving_an_unloaded_column_fails");
+        let open_world = || {
+            let storage = WorldStorage::open(&directory.0).unwrap();
+            let options = WorldOptions {
+                view_distance: 1,
+                ..Default::default()
+            };
+            World::load(storage, options).unwrap()
+        };
+
+        let mut world = open_world();
+        world.movement_mode = MovementMode::Flying;
+        settle(&mut world);
+
+        let edited_block = Coordinates(3, 40, 3);
+        world.set_block(&edited_block, BlockId::LAMP);
+
+        // Region files can't be read or written while a file is in the way of their directory.
+        let regions_directory = directory.0.join("regions");
+        std::fs::remove_dir(&regions_directory).unwrap();
+        std::fs::write(&regions_directory, []).unwrap();
+
+        let starting_position = world.camera.position;
+        world.camera.position = Vec3(10.0 * CHUNK_SIZE as f32, 40.0, 0.0);
+        settle(&mut world);
+
+        while !world.saving_columns.is_empty() {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+            world.update();
+        }
+
+        std::fs::remove_file(&regions_directory).unwrap();
+        std::fs::create_dir(&regions_directory).unwrap();
+
+        // The chunks that failed to save are loaded again in place of generating them, and
+        // they're saved with the rest of the world.
+        world.camera.position = starting_position;
+        settle(&mut world);
+        assert_eq!(world.get_block(&edited_block), BlockId::LAMP);
+
+        world.save().unwrap();
+        drop(world);
+
+        let mut world = open_world();
+        world.movement_mode = MovementMode::Flying;
+        settle(&mut world);
+        assert_eq!(world.get_block(&edited_block), BlockId::LAMP);
+    }
+
+    #[test]
+    fn columns_are_streamed_around_the_camera() {
+        let mut world = World::new(WorldOptions {
+            view_distance: 2,
+            ..Default::default()
+        });
+
+        let updates = settle(&mut world);
+        let expected_columns: HashSet<ColumnCoordinates> =
+            ColumnCoordinates(0, 0).within_distance(2).collect();
+
+        assert_eq!(world.loaded_columns, expected_columns);
+        assert!(updates
+            .iter()
+            .all(|update| matches!(update, ChunkUpdate::Changed(..))));
+
+        world.camera.position = Vec3(10.0 * CHUNK_SIZE as f32, 40.0, 0.0);
+        let updates = settle(&mut world);
+        let expected_columns: HashSet<ColumnCoordinates> =
+            ColumnCoordinates(10, 0).within_distance(2).collect();
+
+        assert_eq!(world.loaded_columns, expected_columns);
+
+        let removed_chunks: HashSet<ChunkCoordinates> = updates
+            .iter()
+            .filter_map(|update| match update {
+                ChunkUpdate::Removed(chunk_coordinates) => Some(*chunk_coordinates),
+                ChunkUpdate::Changed(..) => None,
+            })
+            .collect();
+
+        assert!(removed_chunks.contains(&ChunkCoordinates(0, 0, 0)));
+        assert!(!world.chunks.contains_key(&ChunkCoordinates(0, 0, 0)));
+    }
+
+    #[test]
+    fn limited_worlds_only_load_columns_within_their_size() {
+        let mut world = World::new(WorldOptions {
+            size: Some(2 * CHUNK_SIZE as u32),
+            view_distance: 2,
+            ..Default::default()
+        });
+
+        settle(&mut world);
+
+        let expected_columns = HashSet::from([
+            ColumnCoordinates(0, 0),
+            ColumnCoordinates(0, 1),
+            ColumnCoordinates(1, 0),
+            ColumnCoordinates(1, 1),
+        ]);
+        assert_eq!(world.loaded_columns, expected_columns);
+    }
+
+    #[test]
+    fn chunk_uploads_are_limited_and_closest_first() {
+        let mut world = World::new(WorldOptions {
+            view_distance: 2,
+            ..Default::default()
+        });
+        settle(&mut world);
+
+        // Changing a block on the corner of a chunk also changes the chunks next to that corner.
+        // The block lets light through, so that the light in the chunks around it stays the same.
+        world.set_block(&Coordinates(0, 20, 0), BlockId::LEAVES);
+        world.update();
+
+        while !world.mesh_revisions.is_empty() {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+            world.update();
+        }
+
+        let updates = world.take_chunk_updates(1);
+        assert_eq!(updates.len(), 1);
+        assert!(
+            matches!(
+                updates[0],
+                ChunkUpdate::Changed(ChunkCoordinates(0, 1, 0), _)
+            ),
+            "the chunk closest to the camera should be uploaded first"
+        );
+
+        assert!(!world.take_chunk_updates(usize::MAX).is_empty());
+        assert!(world.take_chunk_updates(usize::MAX).is_empty());
+    }
+
+    /// Creates a world that only loads the column the camera is in, for tests that set up the
+    /// blocks they need themselves.
+    fn test_world() -> World {
+        World::new(WorldOptions {
+            view_distance: 0,
+            meshing_mode: MeshingMode::Naive,
+            ..Default::default()
+        })
+    }
+
+    /// Ticks the blocks in the world without moving the camera or loading any columns.
+    fn run_ticks(world: &mut World, num_ticks: u32) {
+        for _ in 0..num_ticks {
+            world.ticks += 1;
+            world.tick_blocks();
+        }
+    }
+
+    /// Updates the world until every column around the camera has been generated and meshed, and
+    /// returns all of the chunk updates along the way.
+    fn settle(world: &mut World) -> Vec<ChunkUpdate> {
+        let mut updates = Vec::new();
+
+        loop {
+            world.update();
+            updates.extend(world.take_chunk_updates(usize::MAX));
+
+            let camera_column = ColumnCoordinates::containing(&world.camera.position);
+            let all_columns_loaded = camera_column
+                .within_distance(world.view_distance)
+                .filter(|column| world.is_column_in_world(column))
+                .all(|column| world.loaded_columns.contains(&column));
+
+            if all_columns_loaded && world.mesh_revisions.is_empty() {
+                return updates;
+            }
+
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+    }
+
+    /// Returns the number of faces in the meshes of every chunk that changed since the last
+    /// update.
+    fn changed_face_count(world: &mut World) -> usize {
+        let changed_chunks: Vec<ChunkCoordinates> = world.changed_chunks.drain().collect();
+
+        changed_chunks
+            .iter()
+            .map(|chunk_coordinates| {
+                let mesh =
+                    ChunkMesh::build(&world.neighborhood(chunk_coordinates), MeshingMode::Naive);
+                mesh.triangle_count() / 2
+            })
+            .sum()
     }
 }